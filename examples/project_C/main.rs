@@ -38,9 +38,10 @@
 
 // ANCHOR: use
 use argh::FromArgs;
-use log::info;
+use log::{info, warn};
 use std::path::PathBuf;
 use yamake::c_nodes::{AFile, CFile, HFile, OFile, XFile};
+use yamake::command;
 use yamake::model::G;
 // ANCHOR_END: use
 
@@ -64,10 +65,17 @@ struct Args {
     /// sandbox directory
     #[argh(option, short = 'b')]
     sandbox: PathBuf,
+
+    /// print the output of build commands while they run
+    #[argh(switch)]
+    stream: bool,
 }
 
 fn main() {
     env_logger::init();
+    if let Err(e) = command::install_interrupt_handler() {
+        warn!("cannot install Ctrl-C handler: {e}");
+    }
 
     let args: Args = argh::from_env();
 
@@ -77,6 +85,7 @@ fn main() {
     // ANCHOR: instanciate
     let mut g = G::new(srcdir, sandbox);
     // ANCHOR_END: instanciate
+    g.stream_output = args.stream;
 
     // ANCHOR: add_nodes
     let main_c = g.add_root_node(CFile::new("project_C/main.c")).unwrap();
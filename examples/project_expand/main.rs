@@ -50,10 +50,11 @@ mod common;
 
 use argh::FromArgs;
 use common::{JsonDesc, YmlDesc};
-use log::info;
+use log::{info, warn};
 use std::fs;
 use std::path::PathBuf;
 use yamake::c_nodes::{AFile, CFile, HFile, OFile, XFile};
+use yamake::command;
 use yamake::model::G;
 
 /// Command-line arguments for the project_expand example.
@@ -71,6 +72,9 @@ struct Args {
 
 fn main() {
     env_logger::init();
+    if let Err(e) = command::install_interrupt_handler() {
+        warn!("cannot install Ctrl-C handler: {e}");
+    }
 
     let args: Args = argh::from_env();
 
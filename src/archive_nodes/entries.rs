@@ -0,0 +1,156 @@
+use crate::model::GNode;
+use std::collections::BTreeMap;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// A file or symbolic link of an archive.
+pub(crate) struct Entry {
+    /// Path in the archive, with `/` separators.
+    pub archive_path: String,
+    /// Path of the file, in the sandbox.
+    pub source: PathBuf,
+    pub mode: u32,
+    /// Target of a symbolic link, stored as is.
+    pub link: Option<PathBuf>,
+}
+
+impl Entry {
+    /// The line of the entry in the listing logged by the build.
+    pub fn listing(&self) -> String {
+        match &self.link {
+            Some(target) => format!(
+                "{:o} {} -> {}\n",
+                self.mode,
+                self.archive_path,
+                target.display()
+            ),
+            None => format!("{:o} {}\n", self.mode, self.archive_path),
+        }
+    }
+}
+
+/// Where the outputs of the predecessors go in an archive, and with which
+/// permissions. Shared by `TarNode` and `ZipNode`, and set with the methods
+/// of [`ArchiveLayout`].
+#[derive(Debug, Clone, Default)]
+pub struct Layout {
+    /// Directory of all the entries in the archive, e.g. `app-1.0`.
+    pub prefix: String,
+    /// Path in the archive of a predecessor, instead of its pathbuf.
+    pub paths: BTreeMap<PathBuf, String>,
+    /// Permissions of an entry, by path in the archive (without the prefix).
+    pub modes: BTreeMap<String, u32>,
+}
+
+/// The builder methods of the archive nodes, which set their [`Layout`].
+pub trait ArchiveLayout: Sized {
+    fn layout_mut(&mut self) -> &mut Layout;
+
+    /// Puts all the entries in this directory of the archive.
+    fn with_prefix(mut self, prefix: &str) -> Self {
+        self.layout_mut().prefix = prefix.to_string();
+        self
+    }
+
+    /// Stores the output of the predecessor `pathbuf` at `archive_path`.
+    fn with_path(mut self, pathbuf: &str, archive_path: &str) -> Self {
+        self.layout_mut()
+            .paths
+            .insert(PathBuf::from(pathbuf), archive_path.to_string());
+        self
+    }
+
+    /// Permissions of the entry at `archive_path` (without the prefix).
+    fn with_mode(mut self, archive_path: &str, mode: u32) -> Self {
+        self.layout_mut()
+            .modes
+            .insert(archive_path.to_string(), mode);
+        self
+    }
+}
+
+impl Layout {
+    /// The entries of the archive, sorted by path. A directory output adds
+    /// its files and symbolic links. Permissions default to 0755 for an
+    /// executable, 0644 for another file and 0777 for a symbolic link, whose
+    /// target must be relative.
+    pub(crate) fn entries(
+        &self,
+        sandbox: &Path,
+        predecessors: &[&(dyn GNode + Send + Sync)],
+    ) -> Result<Vec<Entry>, String> {
+        let mut files: BTreeMap<String, PathBuf> = BTreeMap::new();
+        for pred in predecessors {
+            let pathbuf = pred.pathbuf();
+            let base = self
+                .paths
+                .get(&pathbuf)
+                .cloned()
+                .unwrap_or_else(|| pathbuf.to_string_lossy().to_string());
+            let source = sandbox.join(&pathbuf);
+            let mut add = |path: String, source: PathBuf| match files.insert(path.clone(), source) {
+                Some(_) => Err(format!("{path} is twice in the archive")),
+                None => Ok(()),
+            };
+            if source.is_dir() {
+                for entry in walkdir::WalkDir::new(&source) {
+                    let entry = entry.map_err(|e| e.to_string())?;
+                    if entry.file_type().is_file() || entry.file_type().is_symlink() {
+                        let relative = entry.path().strip_prefix(&source).unwrap();
+                        add(
+                            format!("{base}/{}", relative.to_string_lossy()),
+                            entry.path().to_path_buf(),
+                        )?;
+                    }
+                }
+            } else {
+                add(base, source)?;
+            }
+        }
+
+        files
+            .into_iter()
+            .map(|(path, source)| {
+                let metadata = fs::symlink_metadata(&source)
+                    .map_err(|e| format!("cannot read {}: {e}", source.display()))?;
+                let link = if metadata.is_symlink() {
+                    let target = fs::read_link(&source)
+                        .map_err(|e| format!("cannot read {}: {e}", source.display()))?;
+                    // The sandbox is not where the archive is extracted
+                    if target.is_absolute() {
+                        return Err(format!(
+                            "{path} is a symbolic link to an absolute path: {}",
+                            target.display()
+                        ));
+                    }
+                    Some(target)
+                } else {
+                    None
+                };
+                let default_mode = match link {
+                    Some(_) => 0o777,
+                    None if metadata.permissions().mode() & 0o111 != 0 => 0o755,
+                    None => 0o644,
+                };
+                let mode = self.modes.get(&path).copied().unwrap_or(default_mode);
+                let archive_path = if self.prefix.is_empty() {
+                    path
+                } else {
+                    format!("{}/{path}", self.prefix.trim_end_matches('/'))
+                };
+                Ok(Entry {
+                    archive_path,
+                    source,
+                    mode,
+                    link,
+                })
+            })
+            .collect()
+    }
+
+    /// The settings of the layout, for the fingerprint of the node.
+    pub(crate) fn fingerprint(&self) -> String {
+        format!("{:?} {:?} {:?}", self.prefix, self.paths, self.modes)
+    }
+}
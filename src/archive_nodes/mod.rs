@@ -0,0 +1,7 @@
+mod entries;
+pub mod tar_node;
+pub mod zip_node;
+
+pub use entries::{ArchiveLayout, Layout};
+pub use tar_node::TarNode;
+pub use zip_node::ZipNode;
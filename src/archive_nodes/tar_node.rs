@@ -0,0 +1,117 @@
+use super::entries::{ArchiveLayout, Entry, Layout};
+use crate::command::log_build;
+use crate::model::GNode;
+use flate2::{Compression, GzBuilder};
+use log::error;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// A tar archive of the outputs of its predecessors, compressed with gzip if
+/// its name ends with `.tar.gz` or `.tgz`.
+///
+/// Each output is stored at its pathbuf, or at the path given with `with_path`,
+/// under the `with_prefix` directory (see [`ArchiveLayout`]); a directory
+/// output adds its files and symbolic links. The archive is deterministic:
+/// entries sorted by path, mtime 0, owner and group 0 without names,
+/// permissions 0755 for executables and 0644 otherwise unless given with
+/// `with_mode`, and no timestamp or file name in the gzip header. Identical
+/// inputs give a byte-identical archive, so that a rebuild with the same inputs
+/// is `BuildNotChanged`.
+pub struct TarNode {
+    pub name: String,
+    layout: Layout,
+}
+
+impl TarNode {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            layout: Layout::default(),
+        }
+    }
+
+    fn gzip(&self) -> bool {
+        self.name.ends_with(".tar.gz") || self.name.ends_with(".tgz")
+    }
+
+    fn write_archive(
+        &self,
+        sandbox: &Path,
+        predecessors: &[&(dyn GNode + Send + Sync)],
+    ) -> Result<String, String> {
+        let entries = self.layout.entries(sandbox, predecessors)?;
+        let file = File::create(sandbox.join(&self.name)).map_err(|e| e.to_string())?;
+        if self.gzip() {
+            let gz = GzBuilder::new()
+                .mtime(0)
+                .write(file, Compression::default());
+            write_tar(gz, &entries)?
+                .finish()
+                .map_err(|e| e.to_string())?;
+        } else {
+            write_tar(file, &entries)?;
+        }
+        Ok(entries.iter().map(Entry::listing).collect())
+    }
+}
+
+/// Writes the entries as a tar stream, and returns the writer.
+fn write_tar<W: Write>(writer: W, entries: &[Entry]) -> Result<W, String> {
+    let mut builder = tar::Builder::new(writer);
+    for entry in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(entry.mode);
+        header.set_mtime(0);
+        header.set_uid(0);
+        header.set_gid(0);
+        if let Some(target) = &entry.link {
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            builder
+                .append_link(&mut header, &entry.archive_path, target)
+                .map_err(|e| format!("cannot add {}: {e}", entry.archive_path))?;
+            continue;
+        }
+        let content = fs::read(&entry.source)
+            .map_err(|e| format!("cannot read {}: {e}", entry.source.display()))?;
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_size(content.len() as u64);
+        builder
+            .append_data(&mut header, &entry.archive_path, content.as_slice())
+            .map_err(|e| format!("cannot add {}: {e}", entry.archive_path))?;
+    }
+    builder.into_inner().map_err(|e| e.to_string())
+}
+
+impl ArchiveLayout for TarNode {
+    fn layout_mut(&mut self) -> &mut Layout {
+        &mut self.layout
+    }
+}
+
+impl GNode for TarNode {
+    fn build(&self, sandbox: &Path, predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
+        let description = format!("Create archive: {}", self.name);
+        match self.write_archive(sandbox, predecessors) {
+            Ok(listing) => log_build(sandbox, &self.name, &description, &listing, ""),
+            Err(e) => {
+                error!("{}: {e}", self.name);
+                log_build(sandbox, &self.name, &description, "", &format!("{e}\n"));
+                false
+            }
+        }
+    }
+
+    fn fingerprint(&self, _predecessors: &[&(dyn GNode + Send + Sync)]) -> Option<String> {
+        Some(self.layout.fingerprint())
+    }
+
+    fn tag(&self) -> String {
+        "TarNode".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
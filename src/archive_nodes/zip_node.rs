@@ -0,0 +1,90 @@
+use super::entries::{ArchiveLayout, Entry, Layout};
+use crate::command::log_build;
+use crate::model::GNode;
+use log::error;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, DateTime, ZipWriter};
+
+/// A zip archive of the outputs of its predecessors, compressed with deflate.
+///
+/// The entries are laid out as in a `TarNode`, with the same `with_prefix`,
+/// `with_path` and `with_mode` of [`ArchiveLayout`]. The archive is
+/// deterministic: entries sorted by path, all dated 1980-01-01 (the earliest
+/// date of the format), and permissions 0755 for executables and 0644 otherwise
+/// unless given.
+pub struct ZipNode {
+    pub name: String,
+    layout: Layout,
+}
+
+impl ZipNode {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            layout: Layout::default(),
+        }
+    }
+
+    fn write_archive(
+        &self,
+        sandbox: &Path,
+        predecessors: &[&(dyn GNode + Send + Sync)],
+    ) -> Result<String, String> {
+        let entries = self.layout.entries(sandbox, predecessors)?;
+        let file = File::create(sandbox.join(&self.name)).map_err(|e| e.to_string())?;
+        let mut zip = ZipWriter::new(file);
+        for entry in &entries {
+            let options = SimpleFileOptions::default()
+                .compression_method(CompressionMethod::Deflated)
+                .last_modified_time(DateTime::default())
+                .unix_permissions(entry.mode);
+            if let Some(target) = &entry.link {
+                zip.add_symlink(&entry.archive_path, target.to_string_lossy(), options)
+                    .map_err(|e| format!("cannot add {}: {e}", entry.archive_path))?;
+                continue;
+            }
+            let content = fs::read(&entry.source)
+                .map_err(|e| format!("cannot read {}: {e}", entry.source.display()))?;
+            zip.start_file(entry.archive_path.as_str(), options)
+                .and_then(|()| zip.write_all(&content).map_err(Into::into))
+                .map_err(|e| format!("cannot add {}: {e}", entry.archive_path))?;
+        }
+        zip.finish().map_err(|e| e.to_string())?;
+        Ok(entries.iter().map(Entry::listing).collect())
+    }
+}
+
+impl ArchiveLayout for ZipNode {
+    fn layout_mut(&mut self) -> &mut Layout {
+        &mut self.layout
+    }
+}
+
+impl GNode for ZipNode {
+    fn build(&self, sandbox: &Path, predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
+        let description = format!("Create archive: {}", self.name);
+        match self.write_archive(sandbox, predecessors) {
+            Ok(listing) => log_build(sandbox, &self.name, &description, &listing, ""),
+            Err(e) => {
+                error!("{}: {e}", self.name);
+                log_build(sandbox, &self.name, &description, "", &format!("{e}\n"));
+                false
+            }
+        }
+    }
+
+    fn fingerprint(&self, _predecessors: &[&(dyn GNode + Send + Sync)]) -> Option<String> {
+        Some(self.layout.fingerprint())
+    }
+
+    fn tag(&self) -> String {
+        "ZipNode".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
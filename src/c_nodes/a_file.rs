@@ -1,32 +1,60 @@
+use crate::c_nodes::Toolchain;
 use crate::command::run_command;
 use crate::model::GNode;
+use log::error;
+use std::fs;
+use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
-use std::process::Command;
 
+/// A static library of its `OFile` and `CxxOFile` predecessors.
+///
+/// The archive is deterministic: members in path order, with `ar D` (zero
+/// timestamps, uids and gids), and written anew at each build. Objects rebuilt
+/// with the same content give the same archive, `BuildNotChanged`, so that the
+/// executables linking it are not linked again.
 pub struct AFile {
     pub name: String,
+    pub toolchain: Toolchain,
 }
 
 impl AFile {
     pub fn new(name: &str) -> Self {
         Self {
             name: name.to_string(),
+            toolchain: Toolchain::default(),
         }
     }
+
+    /// Uses `toolchain` instead of `Toolchain::default()`.
+    pub fn with_toolchain(mut self, toolchain: Toolchain) -> Self {
+        self.toolchain = toolchain;
+        self
+    }
 }
 
 impl GNode for AFile {
     fn build(&self, sandbox: &Path, predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
-        // Only include OFile predecessors in the archive
-        let inputs: Vec<PathBuf> = predecessors
+        // Only include OFile and CxxOFile predecessors in the archive
+        let mut inputs: Vec<PathBuf> = predecessors
             .iter()
-            .filter(|p| p.tag() == "OFile")
+            .filter(|p| p.tag() == "OFile" || p.tag() == "CxxOFile")
             .map(|p| sandbox.join(p.pathbuf()))
             .collect();
+        inputs.sort();
+
+        // `ar r` updates an existing archive, keeping the members of removed
+        // objects and the order of the previous build
+        let output = sandbox.join(&self.name);
+        if let Err(e) = fs::remove_file(&output)
+            && e.kind() != ErrorKind::NotFound
+        {
+            error!("{}: cannot remove the previous archive: {e}", self.name);
+            return false;
+        }
 
-        let mut cmd = Command::new("ar");
-        cmd.arg("rcs");
-        cmd.arg(sandbox.join(&self.name));
+        let mut cmd = self.toolchain.command(&self.toolchain.ar);
+        cmd.arg("rcsD");
+        cmd.arg(output);
         for input in &inputs {
             cmd.arg(input);
         }
@@ -34,6 +62,10 @@ impl GNode for AFile {
         run_command(&mut cmd, sandbox, &self.name)
     }
 
+    fn fingerprint(&self, _predecessors: &[&(dyn GNode + Send + Sync)]) -> Option<String> {
+        Some(format!("{} {:?}", self.toolchain.ar, self.toolchain.env))
+    }
+
     fn tag(&self) -> String {
         "AFile".to_string()
     }
@@ -0,0 +1,24 @@
+use crate::model::GRootNode;
+use std::path::PathBuf;
+
+pub struct CppFile {
+    pub name: String,
+}
+
+impl CppFile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+        }
+    }
+}
+
+impl GRootNode for CppFile {
+    fn tag(&self) -> String {
+        "CppFile".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
@@ -0,0 +1,24 @@
+use crate::model::GRootNode;
+use std::path::PathBuf;
+
+pub struct HppFile {
+    pub name: String,
+}
+
+impl HppFile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+        }
+    }
+}
+
+impl GRootNode for HppFile {
+    fn tag(&self) -> String {
+        "HppFile".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
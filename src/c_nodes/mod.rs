@@ -1,11 +1,21 @@
 pub mod a_file;
 pub mod c_file;
+pub mod cpp_file;
 pub mod h_file;
+pub mod hpp_file;
 pub mod o_file;
+pub mod so_file;
+pub mod test_run;
+pub mod toolchain;
 pub mod x_file;
 
 pub use a_file::AFile;
 pub use c_file::CFile;
+pub use cpp_file::CppFile;
 pub use h_file::HFile;
+pub use hpp_file::HppFile;
 pub use o_file::OFile;
+pub use so_file::SoFile;
+pub use test_run::TestRun;
+pub use toolchain::Toolchain;
 pub use x_file::XFile;
@@ -1,5 +1,7 @@
+use crate::c_nodes::Toolchain;
 use crate::command::run_command;
-use crate::model::GNode;
+use crate::depfile;
+use crate::model::{CompileCommand, GNode};
 use log::{info, warn};
 use regex::Regex;
 use std::collections::HashSet;
@@ -11,13 +13,30 @@ pub struct OFile {
     pub name: String,
     pub include_paths: Vec<PathBuf>,
     pub compile_flags: Vec<String>,
+    /// Compile with `-MD -MF` and store the dependencies of the depfile in the report.
+    pub use_depfile: bool,
+    /// Also follow `#include <...>` through the include paths, and return the
+    /// headers found outside the sandbox as external dependencies.
+    pub follow_angle_includes: bool,
+    pub toolchain: Toolchain,
+    /// Compile with `-fPIC`. Set by `make()` when the object is linked into an
+    /// `SoFile`, or with [`OFile::with_pic`].
+    pub pic: bool,
+    /// Compile `CppFile` sources with the C++ compiler, see [`OFile::new_cxx`].
+    pub cxx: bool,
+}
+
+/// How the include directives are resolved while scanning.
+struct ScanContext<'a> {
+    sandbox: &'a Path,
+    include_re: Regex,
+    include_paths: &'a [PathBuf],
+    follow_angle_includes: bool,
 }
 
 fn scan_file_recursive(
-    sandbox: &Path,
+    ctx: &ScanContext,
     file_path: &Path,
-    include_re: &Regex,
-    include_paths: &[PathBuf],
     result: &mut Vec<PathBuf>,
     visited: &mut HashSet<PathBuf>,
     scan_complete: &mut bool,
@@ -32,8 +51,12 @@ fn scan_file_recursive(
         }
     };
 
-    for cap in include_re.captures_iter(&content) {
-        let header = &cap[1];
+    for cap in ctx.include_re.captures_iter(&content) {
+        let (header, angle) = match (cap.get(1), cap.get(2)) {
+            (Some(quoted), _) => (quoted.as_str(), false),
+            (None, Some(angle)) if ctx.follow_angle_includes => (angle.as_str(), true),
+            _ => continue,
+        };
         let header_path = PathBuf::from(header);
 
         // Skip if already visited
@@ -42,31 +65,39 @@ fn scan_file_recursive(
         }
 
         visited.insert(header_path.clone());
-        result.push(header_path.clone());
 
         // Try to find the header file in sandbox or include paths
-        let sandbox_header = sandbox.join(&header_path);
+        let sandbox_header = ctx.sandbox.join(&header_path);
         let found_path = if sandbox_header.exists() {
             Some(sandbox_header)
         } else {
-            // Check include paths
-            include_paths
+            // Check include paths, relative to the sandbox as on the gcc command line
+            ctx.include_paths
                 .iter()
-                .map(|p| p.join(&header_path))
+                .map(|p| ctx.sandbox.join(p).join(&header_path))
                 .find(|p| p.exists())
         };
 
+        match &found_path {
+            // Headers outside the sandbox are external dependencies, tracked by digest
+            Some(actual_path)
+                if ctx.follow_angle_includes && !actual_path.starts_with(ctx.sandbox) =>
+            {
+                result.push(actual_path.clone())
+            }
+            // A header of the sandbox, e.g. found through a relative include path
+            Some(actual_path) => match actual_path.strip_prefix(ctx.sandbox) {
+                Ok(relative) => result.push(relative.to_path_buf()),
+                Err(_) => result.push(header_path.clone()),
+            },
+            // A system header that is not in the include paths is not tracked
+            None if angle => continue,
+            None => result.push(header_path.clone()),
+        }
+
         // Recursively scan the header file if found
         if let Some(actual_path) = found_path {
-            scan_file_recursive(
-                sandbox,
-                &actual_path,
-                include_re,
-                include_paths,
-                result,
-                visited,
-                scan_complete,
-            );
+            scan_file_recursive(ctx, &actual_path, result, visited, scan_complete);
         } else {
             // File doesn't exist in sandbox or include paths - might be generated later
             warn!(
@@ -84,36 +115,148 @@ impl OFile {
             name: name.to_string(),
             include_paths,
             compile_flags,
+            use_depfile: false,
+            follow_angle_includes: false,
+            toolchain: Toolchain::default(),
+            pic: false,
+            cxx: false,
         }
     }
+
+    /// An object file compiled from `CppFile` sources with the C++ compiler
+    /// and `cxxflags` of its toolchain. Its tag is `CxxOFile`, so that `XFile`
+    /// and `SoFile` link it with the C++ driver.
+    pub fn new_cxx(name: &str, include_paths: Vec<PathBuf>, compile_flags: Vec<String>) -> Self {
+        Self {
+            cxx: true,
+            ..Self::new(name, include_paths, compile_flags)
+        }
+    }
+
+    /// Tag of the source files of this object file.
+    fn source_tag(&self) -> &str {
+        if self.cxx { "CppFile" } else { "CFile" }
+    }
+
+    /// The compiler and its default flags.
+    fn compiler(&self) -> (&str, &[String]) {
+        if self.cxx {
+            (&self.toolchain.cxx, &self.toolchain.cxxflags)
+        } else {
+            (&self.toolchain.cc, &self.toolchain.cflags)
+        }
+    }
+
+    /// Makes gcc write a depfile (`-MD -MF`) next to the object file.
+    ///
+    /// After each successful build, the dependencies of the depfile are stored
+    /// in the report. They are the authoritative header dependencies on the
+    /// next runs: they follow `#if` guards, `<...>` and macro includes, and the
+    /// include path resolution of gcc. The regex scan is only used to bootstrap
+    /// the first build.
+    pub fn with_depfile(mut self) -> Self {
+        self.use_depfile = true;
+        self
+    }
+
+    /// Follows `#include <...>` through the include paths while scanning.
+    ///
+    /// Headers found outside the sandbox, through an absolute include path,
+    /// are returned by `scan` as absolute paths: `make()` records their digest
+    /// in the report and recompiles the object file when one of them changes.
+    /// Angle includes that are not found (`<stdio.h>` when `/usr/include` is
+    /// not an include path) are ignored. With a depfile, the headers it lists
+    /// outside the sandbox are kept the same way.
+    pub fn with_angle_includes(mut self) -> Self {
+        self.follow_angle_includes = true;
+        self
+    }
+
+    /// Compiles with `-fPIC`, for an object linked into an `SoFile` through an
+    /// `AFile`. `make()` sets it for the objects linked directly.
+    pub fn with_pic(mut self) -> Self {
+        self.pic = true;
+        self
+    }
+
+    /// Uses `toolchain` instead of `Toolchain::default()`.
+    pub fn with_toolchain(mut self, toolchain: Toolchain) -> Self {
+        self.toolchain = toolchain;
+        self
+    }
+
+    /// Path of the depfile, relative to the sandbox.
+    pub fn depfile(&self) -> PathBuf {
+        PathBuf::from(&self.name).with_extension("d")
+    }
 }
 
-impl GNode for OFile {
-    fn build(&self, sandbox: &Path, predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
-        // Only include CFile predecessors as source files - headers are included via -I
-        let inputs: Vec<PathBuf> = predecessors
+impl OFile {
+    /// Source files of the object file: the CFile (CppFile) predecessors.
+    fn inputs(&self, sandbox: &Path, predecessors: &[&(dyn GNode + Send + Sync)]) -> Vec<PathBuf> {
+        // Headers are not inputs, they are included via -I
+        predecessors
             .iter()
-            .filter(|p| p.tag() == "CFile")
+            .filter(|p| p.tag() == self.source_tag())
             .map(|p| sandbox.join(p.pathbuf()))
-            .collect();
+            .collect()
+    }
+
+    /// The compiler command run by `build`.
+    pub fn command(&self, sandbox: &Path, predecessors: &[&(dyn GNode + Send + Sync)]) -> Command {
+        let inputs = self.inputs(sandbox, predecessors);
 
-        let mut cmd = Command::new("gcc");
+        let (compiler, default_flags) = self.compiler();
+        let mut cmd = self.toolchain.command(compiler);
         cmd.arg("-c");
-        for flag in &self.compile_flags {
+        for flag in default_flags.iter().chain(&self.compile_flags) {
             cmd.arg(flag);
         }
+        if self.pic {
+            cmd.arg("-fPIC");
+        }
         cmd.arg("-I").arg(sandbox);
         for include_path in &self.include_paths {
             cmd.arg("-I").arg(sandbox.join(include_path));
         }
+        if self.use_depfile {
+            cmd.arg("-MD").arg("-MF").arg(sandbox.join(self.depfile()));
+        }
         cmd.arg("-o").arg(sandbox.join(&self.name));
         for input in &inputs {
             cmd.arg(input);
         }
+        cmd
+    }
+}
 
+impl GNode for OFile {
+    fn build(&self, sandbox: &Path, predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
+        let mut cmd = self.command(sandbox, predecessors);
         run_command(&mut cmd, sandbox, &self.name)
     }
 
+    fn compile_commands(
+        &self,
+        sandbox: &Path,
+        predecessors: &[&(dyn GNode + Send + Sync)],
+    ) -> Vec<CompileCommand> {
+        let cmd = self.command(sandbox, predecessors);
+        let arguments: Vec<String> = std::iter::once(cmd.get_program())
+            .chain(cmd.get_args())
+            .map(|a| a.to_string_lossy().to_string())
+            .collect();
+        self.inputs(sandbox, predecessors)
+            .into_iter()
+            .map(|file| CompileCommand {
+                directory: sandbox.to_path_buf(),
+                file,
+                arguments: arguments.clone(),
+                output: sandbox.join(&self.name),
+            })
+            .collect()
+    }
+
     fn scan(
         &self,
         sandbox: &Path,
@@ -123,16 +266,19 @@ impl GNode for OFile {
         let mut result = Vec::new();
         let mut visited = HashSet::new();
         let mut scan_complete = true;
-        let include_re = Regex::new(r#"(?m)^\s*#include\s+"([^"]+)""#).unwrap();
+        let ctx = ScanContext {
+            sandbox,
+            include_re: Regex::new(r#"(?m)^\s*#include\s+(?:"([^"]+)"|<([^>]+)>)"#).unwrap(),
+            include_paths: &self.include_paths,
+            follow_angle_includes: self.follow_angle_includes,
+        };
 
-        // Scan C files for includes
-        for pred in predecessors.iter().filter(|p| p.tag() == "CFile") {
+        // Scan C (C++) files for includes
+        for pred in predecessors.iter().filter(|p| p.tag() == self.source_tag()) {
             let file_path = sandbox.join(pred.pathbuf());
             scan_file_recursive(
-                sandbox,
+                &ctx,
                 &file_path,
-                &include_re,
-                &self.include_paths,
                 &mut result,
                 &mut visited,
                 &mut scan_complete,
@@ -146,8 +292,38 @@ impl GNode for OFile {
         (scan_complete, result)
     }
 
+    fn discovered_dependencies(&self, sandbox: &Path) -> Option<Vec<PathBuf>> {
+        if !self.use_depfile {
+            return None;
+        }
+        let content = fs::read_to_string(sandbox.join(self.depfile())).ok()?;
+        let deps = depfile::prerequisites(&content);
+        Some(if self.follow_angle_includes {
+            depfile::normalize_paths(sandbox, deps)
+        } else {
+            depfile::relativize(sandbox, deps)
+        })
+    }
+
+    fn fingerprint(&self, _predecessors: &[&(dyn GNode + Send + Sync)]) -> Option<String> {
+        let (compiler, default_flags) = self.compiler();
+        Some(format!(
+            "{} {:?} {:?} {:?} {} {:?}",
+            compiler,
+            default_flags,
+            self.compile_flags,
+            self.include_paths,
+            self.pic,
+            self.toolchain.env
+        ))
+    }
+
+    fn set_position_independent(&mut self) {
+        self.pic = true;
+    }
+
     fn tag(&self) -> String {
-        "OFile".to_string()
+        if self.cxx { "CxxOFile" } else { "OFile" }.to_string()
     }
 
     fn pathbuf(&self) -> PathBuf {
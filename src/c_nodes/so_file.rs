@@ -0,0 +1,109 @@
+use crate::c_nodes::Toolchain;
+use crate::command::run_command;
+use crate::model::GNode;
+use std::path::{Path, PathBuf};
+
+/// A shared library, linked with `-shared` from its `OFile` and `AFile` predecessors.
+///
+/// The `OFile` predecessors are compiled with `-fPIC` by `make()`. Objects
+/// reaching the library through an `AFile` are not: build them with
+/// [`OFile::with_pic`](crate::c_nodes::OFile::with_pic).
+/// The C++ driver of the toolchain is used when a `CxxOFile` is linked.
+pub struct SoFile {
+    pub name: String,
+    pub toolchain: Toolchain,
+    /// Written in the library as `DT_SONAME`, e.g. `libplugin.so.1`.
+    pub soname: Option<String>,
+    /// Linker version script, relative to the sandbox. Add it as a root node
+    /// and a predecessor of the library so that a change relinks it.
+    pub version_script: Option<PathBuf>,
+}
+
+impl SoFile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            toolchain: Toolchain::default(),
+            soname: None,
+            version_script: None,
+        }
+    }
+
+    /// Uses `toolchain` instead of `Toolchain::default()`.
+    pub fn with_toolchain(mut self, toolchain: Toolchain) -> Self {
+        self.toolchain = toolchain;
+        self
+    }
+
+    /// Sets the soname of the library (`-Wl,-soname`).
+    pub fn with_soname(mut self, soname: &str) -> Self {
+        self.soname = Some(soname.to_string());
+        self
+    }
+
+    /// Sets the version script of the library (`-Wl,--version-script`).
+    pub fn with_version_script(mut self, path: &str) -> Self {
+        self.version_script = Some(PathBuf::from(path));
+        self
+    }
+}
+
+impl GNode for SoFile {
+    fn build(&self, sandbox: &Path, predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
+        // Objects first, then archives; the version script is not an input
+        let mut objects: Vec<PathBuf> = Vec::new();
+        let mut libraries: Vec<PathBuf> = Vec::new();
+        let mut cxx = false;
+        for p in predecessors {
+            let path = sandbox.join(p.pathbuf());
+            match p.tag().as_str() {
+                "OFile" => objects.push(path),
+                "CxxOFile" => {
+                    cxx = true;
+                    objects.push(path)
+                }
+                "AFile" => libraries.push(path),
+                _ => {}
+            }
+        }
+
+        let mut cmd = self.toolchain.command(self.toolchain.linker(cxx));
+        cmd.arg("-shared");
+        cmd.args(&self.toolchain.ldflags);
+        if let Some(soname) = &self.soname {
+            cmd.arg(format!("-Wl,-soname,{soname}"));
+        }
+        if let Some(script) = &self.version_script {
+            cmd.arg(format!(
+                "-Wl,--version-script={}",
+                sandbox.join(script).display()
+            ));
+        }
+        cmd.arg("-o").arg(sandbox.join(&self.name));
+        for input in objects.iter().chain(&libraries) {
+            cmd.arg(input);
+        }
+
+        run_command(&mut cmd, sandbox, &self.name)
+    }
+
+    fn fingerprint(&self, predecessors: &[&(dyn GNode + Send + Sync)]) -> Option<String> {
+        let cxx = predecessors.iter().any(|p| p.tag() == "CxxOFile");
+        Some(format!(
+            "{} {:?} {:?} {:?} {:?}",
+            self.toolchain.linker(cxx),
+            self.toolchain.ldflags,
+            self.soname,
+            self.version_script,
+            self.toolchain.env
+        ))
+    }
+
+    fn tag(&self) -> String {
+        "SoFile".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
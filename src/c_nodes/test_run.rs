@@ -0,0 +1,174 @@
+use crate::command::{last_exit_code, read_stdout_log, run_command, run_command_with_stdin};
+use crate::model::GNode;
+use log::error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Runs the `XFile` predecessor in the sandbox and checks its result.
+///
+/// The test passes if the exit code is the expected one and, with a golden
+/// file, if the standard output is byte for byte equal to it (line endings and
+/// final newline included). Its output is a result file, written only when the
+/// test passes: the test runs again when the executable, the golden file or
+/// the settings change, or after a failure. The command and its output are in
+/// the logs of the node, and `make-report.yml` has a `test_result` for it.
+pub struct TestRun {
+    pub name: String,
+    pub args: Vec<String>,
+    /// Standard input of the executable.
+    pub stdin: Option<String>,
+    pub expected_exit_code: i32,
+    /// Expected standard output, relative to the sandbox. Add it as a root
+    /// node and a predecessor of the test so that a change runs it again.
+    pub golden: Option<PathBuf>,
+}
+
+impl TestRun {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            args: Vec::new(),
+            stdin: None,
+            expected_exit_code: 0,
+            golden: None,
+        }
+    }
+
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args.extend(args);
+        self
+    }
+
+    pub fn with_stdin(mut self, stdin: &str) -> Self {
+        self.stdin = Some(stdin.to_string());
+        self
+    }
+
+    pub fn with_expected_exit_code(mut self, code: i32) -> Self {
+        self.expected_exit_code = code;
+        self
+    }
+
+    pub fn with_golden(mut self, path: &str) -> Self {
+        self.golden = Some(PathBuf::from(path));
+        self
+    }
+
+    /// Appends the reason of a failure to the stderr log of the node.
+    fn log_failure(&self, sandbox: &Path, message: &str) {
+        error!("test {} failed: {message}", self.name);
+        let stderr_path = sandbox.join("logs").join(format!("{}.stderr", self.name));
+        if let Ok(mut file) = fs::OpenOptions::new().append(true).open(stderr_path) {
+            let _ = writeln!(file, "yamake: {message}");
+        }
+    }
+
+    fn run(&self, sandbox: &Path, executable: &Path) -> Result<(), String> {
+        let mut cmd = Command::new(sandbox.join(executable));
+        cmd.current_dir(sandbox).args(&self.args);
+        let succeeded = match &self.stdin {
+            Some(stdin) => {
+                // Next to the other logs of the node, not a file of the sandbox
+                let stdin_path = sandbox.join("logs").join(format!("{}.stdin", self.name));
+                if let Some(parent) = stdin_path.parent() {
+                    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                fs::write(&stdin_path, stdin).map_err(|e| e.to_string())?;
+                run_command_with_stdin(&mut cmd, sandbox, &self.name, &stdin_path)
+            }
+            None => run_command(&mut cmd, sandbox, &self.name),
+        };
+
+        let exit_code = last_exit_code(sandbox, &self.name);
+        if exit_code != Some(self.expected_exit_code) {
+            return Err(match exit_code {
+                Some(code) => format!("exit code {code}, expected {}", self.expected_exit_code),
+                None if succeeded => "no exit code".to_string(),
+                None => "the executable did not run to completion".to_string(),
+            });
+        }
+
+        if let Some(golden) = &self.golden {
+            let expected = fs::read(sandbox.join(golden))
+                .map_err(|e| format!("cannot read {}: {e}", golden.display()))?;
+            let actual = read_stdout_log(sandbox, &self.name).unwrap_or_default();
+            if actual != expected {
+                return Err(first_difference(&actual, &expected, golden));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Describes the first line where the standard output differs from the golden
+/// file. Line endings are kept, so that a `\r` or a missing final newline shows.
+fn first_difference(actual: &[u8], expected: &[u8], golden: &Path) -> String {
+    let actual = String::from_utf8_lossy(actual);
+    let expected = String::from_utf8_lossy(expected);
+    let actual: Vec<&str> = actual.split_inclusive('\n').collect();
+    let expected: Vec<&str> = expected.split_inclusive('\n').collect();
+    let describe =
+        |line: Option<&&str>| line.map_or("the end of output".to_string(), |l| format!("{l:?}"));
+    let n = (0..actual.len().max(expected.len()))
+        .find(|&n| actual.get(n) != expected.get(n))
+        // Same text once decoded: the bytes differ in invalid UTF-8
+        .unwrap_or(0);
+    format!(
+        "stdout differs from {} at line {}: {} instead of {}",
+        golden.display(),
+        n + 1,
+        describe(actual.get(n)),
+        describe(expected.get(n))
+    )
+}
+
+impl GNode for TestRun {
+    fn build(&self, sandbox: &Path, predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
+        let output = sandbox.join(&self.name);
+        // A failed test leaves no result file, so that it runs again
+        let _ = fs::remove_file(&output);
+
+        let Some(executable) = predecessors.iter().find(|p| p.tag() == "XFile") else {
+            error!("test {} has no XFile predecessor", self.name);
+            return false;
+        };
+        let executable = executable.pathbuf();
+
+        match self.run(sandbox, &executable) {
+            Ok(()) => {
+                let result = format!(
+                    "executable: {}\nargs: {:?}\nexit_code: {}\nresult: passed\n",
+                    executable.display(),
+                    self.args,
+                    self.expected_exit_code
+                );
+                fs::write(&output, result).is_ok()
+            }
+            Err(message) => {
+                self.log_failure(sandbox, &message);
+                false
+            }
+        }
+    }
+
+    fn fingerprint(&self, _predecessors: &[&(dyn GNode + Send + Sync)]) -> Option<String> {
+        Some(format!(
+            "{:?} {:?} {} {:?}",
+            self.args, self.stdin, self.expected_exit_code, self.golden
+        ))
+    }
+
+    fn is_test(&self) -> bool {
+        true
+    }
+
+    fn tag(&self) -> String {
+        "TestRun".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
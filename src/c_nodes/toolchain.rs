@@ -0,0 +1,109 @@
+use std::env;
+use std::process::Command;
+
+/// The programs and default flags used by the C nodes.
+///
+/// `Toolchain::default()` reads `CC`, `CXX`, `AR`, `CFLAGS`, `CXXFLAGS` and
+/// `LDFLAGS` from the environment, falling back to `gcc`, `g++` and `ar`.
+/// The linker driver `ld` defaults to the C compiler: it is called as
+/// `$ld -o app objects... archives...`, not as a bare `ld`.
+///
+/// ```
+/// use yamake::c_nodes::{OFile, Toolchain};
+///
+/// let clang = Toolchain {
+///     cc: "clang".to_string(),
+///     ld: "clang".to_string(),
+///     ..Toolchain::gnu()
+/// };
+/// let main_o = OFile::new("project_C/main.o", vec![], vec![]).with_toolchain(clang);
+///
+/// let aarch64 = Toolchain::cross("aarch64-linux-gnu-");
+/// assert_eq!(aarch64.cc, "aarch64-linux-gnu-gcc");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Toolchain {
+    /// C compiler.
+    pub cc: String,
+    /// C++ compiler.
+    pub cxx: String,
+    /// Archiver.
+    pub ar: String,
+    /// Linker driver.
+    pub ld: String,
+    /// Flags passed to the C compiler before the flags of the node.
+    pub cflags: Vec<String>,
+    /// Flags passed to the C++ compiler before the flags of the node.
+    pub cxxflags: Vec<String>,
+    /// Flags passed to the linker driver.
+    pub ldflags: Vec<String>,
+    /// Environment variables set for every command of the toolchain.
+    pub env: Vec<(String, String)>,
+}
+
+impl Toolchain {
+    /// The GNU toolchain found in `PATH`, without default flags.
+    pub fn gnu() -> Self {
+        Self::cross("")
+    }
+
+    /// A GNU cross toolchain: `<prefix>gcc`, `<prefix>g++` and `<prefix>ar`.
+    pub fn cross(prefix: &str) -> Self {
+        Self {
+            cc: format!("{prefix}gcc"),
+            cxx: format!("{prefix}g++"),
+            ar: format!("{prefix}ar"),
+            ld: format!("{prefix}gcc"),
+            cflags: Vec::new(),
+            cxxflags: Vec::new(),
+            ldflags: Vec::new(),
+            env: Vec::new(),
+        }
+    }
+
+    /// The GNU toolchain, overridden by the `CC`, `CXX`, `AR`, `CFLAGS`,
+    /// `CXXFLAGS` and `LDFLAGS` environment variables.
+    pub fn from_env() -> Self {
+        let var = |name: &str| env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let flags = |name: &str| {
+            var(name)
+                .map(|v| v.split_whitespace().map(str::to_string).collect())
+                .unwrap_or_default()
+        };
+        let gnu = Self::gnu();
+        let cc = var("CC").unwrap_or(gnu.cc);
+        Self {
+            ld: cc.clone(),
+            cc,
+            cxx: var("CXX").unwrap_or(gnu.cxx),
+            ar: var("AR").unwrap_or(gnu.ar),
+            cflags: flags("CFLAGS"),
+            cxxflags: flags("CXXFLAGS"),
+            ldflags: flags("LDFLAGS"),
+            env: Vec::new(),
+        }
+    }
+
+    /// The linker driver: `ld`, or `cxx` for C++ objects when `ld` is the C
+    /// compiler, so that the C++ runtime is linked.
+    pub fn linker(&self, cxx: bool) -> &str {
+        if cxx && self.ld == self.cc {
+            &self.cxx
+        } else {
+            &self.ld
+        }
+    }
+
+    /// A command running `program` with the environment of the toolchain.
+    pub fn command(&self, program: &str) -> Command {
+        let mut cmd = Command::new(program);
+        cmd.envs(self.env.iter().map(|(k, v)| (k, v)));
+        cmd
+    }
+}
+
+impl Default for Toolchain {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
@@ -1,18 +1,77 @@
+use crate::c_nodes::Toolchain;
 use crate::command::run_command;
 use crate::model::GNode;
 use std::path::{Path, PathBuf};
-use std::process::Command;
 
+/// An executable, linked from its `OFile`, `AFile` and `SoFile` predecessors.
+///
+/// The C++ driver of the toolchain is used when a `CxxOFile` is linked. C++
+/// objects inside an `AFile` are not seen: link `stdc++` with `with_libs`.
 pub struct XFile {
     pub name: String,
+    pub toolchain: Toolchain,
+    /// Flags passed to the linker driver before the inputs, e.g. `-Wl,--as-needed`.
+    pub link_flags: Vec<String>,
+    /// Library search paths (`-L`), relative to the sandbox unless absolute.
+    pub lib_paths: Vec<PathBuf>,
+    /// System libraries (`-l`), linked after the objects and archives.
+    pub libs: Vec<String>,
 }
 
 impl XFile {
     pub fn new(name: &str) -> Self {
         Self {
             name: name.to_string(),
+            toolchain: Toolchain::default(),
+            link_flags: Vec::new(),
+            lib_paths: Vec::new(),
+            libs: Vec::new(),
         }
     }
+
+    /// Uses `toolchain` instead of `Toolchain::default()`.
+    pub fn with_toolchain(mut self, toolchain: Toolchain) -> Self {
+        self.toolchain = toolchain;
+        self
+    }
+
+    /// Adds flags passed to the linker driver, e.g. `-pthread` or `-Wl,-z,now`.
+    pub fn with_link_flags(mut self, flags: Vec<String>) -> Self {
+        self.link_flags.extend(flags);
+        self
+    }
+
+    /// Adds library search paths, passed as `-L`.
+    pub fn with_lib_paths(mut self, paths: Vec<PathBuf>) -> Self {
+        self.lib_paths.extend(paths);
+        self
+    }
+
+    /// Adds libraries, passed as `-l<name>` after the archives: `m` for `-lm`.
+    pub fn with_libs(mut self, libs: Vec<String>) -> Self {
+        self.libs.extend(libs);
+        self
+    }
+}
+
+/// Path of `to` relative to `from`, both directories relative to the sandbox.
+fn relative_dir(from: &Path, to: &Path) -> PathBuf {
+    let common = from
+        .components()
+        .zip(to.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut result = PathBuf::new();
+    for _ in from.components().skip(common) {
+        result.push("..");
+    }
+    for c in to.components().skip(common) {
+        result.push(c);
+    }
+    if result.as_os_str().is_empty() {
+        result.push(".");
+    }
+    result
 }
 
 impl GNode for XFile {
@@ -21,31 +80,75 @@ impl GNode for XFile {
         // Separate object files and libraries - libraries must come last for Linux linker
         let mut objects: Vec<PathBuf> = Vec::new();
         let mut libraries: Vec<PathBuf> = Vec::new();
+        let mut shared: Vec<PathBuf> = Vec::new();
+        let mut cxx = false;
 
         for p in predecessors {
             let path = sandbox.join(p.pathbuf());
             if p.tag() == "AFile" {
                 libraries.push(path);
+            } else if p.tag() == "SoFile" {
+                shared.push(p.pathbuf());
             } else {
+                cxx |= p.tag() == "CxxOFile";
                 objects.push(path);
             }
         }
         // ANCHOR_END: tag-usage
 
-        let mut cmd = Command::new("gcc");
+        let mut cmd = self.toolchain.command(self.toolchain.linker(cxx));
+        cmd.args(&self.toolchain.ldflags);
+        cmd.args(&self.link_flags);
         cmd.arg("-o").arg(sandbox.join(&self.name));
 
-        // Add object files first, then libraries
+        // Add object files first, then archives, then shared and system libraries
         for obj in &objects {
             cmd.arg(obj);
         }
         for lib in &libraries {
             cmd.arg(lib);
         }
+        // Shared libraries are found at run time through an rpath relative to
+        // the executable, so that the sandbox can be moved
+        let mut rpaths: Vec<PathBuf> = Vec::new();
+        for lib in &shared {
+            let dir = lib.parent().unwrap_or(Path::new(""));
+            let Some(file_name) = lib.file_name() else {
+                continue;
+            };
+            cmd.arg(format!("-L{}", sandbox.join(dir).display()));
+            cmd.arg(format!("-l:{}", file_name.to_string_lossy()));
+            let rpath = relative_dir(self.pathbuf().parent().unwrap_or(Path::new("")), dir);
+            if !rpaths.contains(&rpath) {
+                rpaths.push(rpath);
+            }
+        }
+        for rpath in &rpaths {
+            cmd.arg(format!("-Wl,-rpath,$ORIGIN/{}", rpath.display()));
+        }
+        for path in &self.lib_paths {
+            cmd.arg(format!("-L{}", sandbox.join(path).display()));
+        }
+        for lib in &self.libs {
+            cmd.arg(format!("-l{lib}"));
+        }
 
         run_command(&mut cmd, sandbox, &self.name)
     }
 
+    fn fingerprint(&self, predecessors: &[&(dyn GNode + Send + Sync)]) -> Option<String> {
+        let cxx = predecessors.iter().any(|p| p.tag() == "CxxOFile");
+        Some(format!(
+            "{} {:?} {:?} {:?} {:?} {:?}",
+            self.toolchain.linker(cxx),
+            self.toolchain.ldflags,
+            self.link_flags,
+            self.lib_paths,
+            self.libs,
+            self.toolchain.env
+        ))
+    }
+
     fn tag(&self) -> String {
         "XFile".to_string()
     }
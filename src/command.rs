@@ -1,8 +1,370 @@
-use log::info;
+use log::{info, warn};
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
-use std::io::Write;
-use std::path::Path;
-use std::process::Command;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{LazyLock, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Time between SIGTERM and SIGKILL when a command is terminated.
+const TERMINATE_GRACE: Duration = Duration::from_secs(2);
+
+/// Child processes started by `run_command` for one sandbox.
+#[derive(Default)]
+struct SandboxProcesses {
+    cancelled: bool,
+    /// Running children, with the time SIGTERM was sent to their process group.
+    pids: HashMap<u32, Option<Instant>>,
+    /// Timeout for commands of nodes that have no timeout of their own.
+    default_timeout: Option<Duration>,
+    /// Timeout per node id.
+    node_timeouts: HashMap<String, Duration>,
+    /// Node ids whose command was terminated because of a timeout.
+    timed_out: HashSet<String>,
+    /// Exit code of the last command of each node id.
+    exit_codes: HashMap<String, i32>,
+    /// Copy the output of commands to the terminal while they run.
+    stream_output: bool,
+}
+
+/// Running child processes, keyed by sandbox.
+static PROCESSES: LazyLock<Mutex<HashMap<PathBuf, SandboxProcesses>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Set by the Ctrl-C handler, cleared at the start of `make()`.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Sends a signal to the process group of a child.
+///
+/// Children are spawned as process group leaders, so this also reaches the
+/// processes they started.
+fn signal_group(pid: u32, signal: libc::c_int) {
+    // SAFETY: the pid is a child that has not been reaped yet (reaping
+    // happens while holding the lock), so its group id cannot have been reused.
+    unsafe {
+        libc::killpg(pid as libc::pid_t, signal);
+    }
+}
+
+/// Sends SIGTERM to a registered child, and SIGKILL after the grace period if
+/// it is still registered then.
+fn terminate(sandbox: &Path, entry: &mut SandboxProcesses, pid: u32) {
+    info!("Terminating process group {pid}");
+    signal_group(pid, libc::SIGTERM);
+    entry.pids.insert(pid, Some(Instant::now()));
+    let sandbox = sandbox.to_path_buf();
+    thread::spawn(move || {
+        thread::sleep(TERMINATE_GRACE);
+        let processes = PROCESSES.lock().unwrap();
+        if processes
+            .get(&sandbox)
+            .is_some_and(|entry| entry.pids.contains_key(&pid))
+        {
+            signal_group(pid, libc::SIGKILL);
+        }
+    });
+}
+
+/// Terminates every running child of the sandbox that was not signalled yet.
+fn terminate_all(sandbox: &Path, entry: &mut SandboxProcesses) {
+    let pids: Vec<u32> = entry
+        .pids
+        .iter()
+        .filter(|(_, terminated_at)| terminated_at.is_none())
+        .map(|(pid, _)| *pid)
+        .collect();
+    for pid in pids {
+        terminate(sandbox, entry, pid);
+    }
+}
+
+/// Cancels all commands run by `run_command` in this sandbox.
+///
+/// Running child processes get SIGTERM, then SIGKILL if they are still running
+/// after a grace period. Later calls to `run_command` for this sandbox fail without
+/// starting anything, until the next `make()`.
+pub fn cancel_commands(sandbox: &Path) {
+    let mut processes = PROCESSES.lock().unwrap();
+    let entry = processes.entry(sandbox.to_path_buf()).or_default();
+    entry.cancelled = true;
+    terminate_all(sandbox, entry);
+}
+
+/// Returns true if `cancel_commands` was called for this sandbox.
+pub fn is_cancelled(sandbox: &Path) -> bool {
+    PROCESSES
+        .lock()
+        .unwrap()
+        .get(sandbox)
+        .is_some_and(|entry| entry.cancelled)
+}
+
+/// Prepares a sandbox for a new `make()`: clears the cancelled flag and timed out
+/// nodes, and sets whether command output is streamed to the terminal.
+pub(crate) fn reset_sandbox(sandbox: &Path, stream_output: bool) {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+    let mut processes = PROCESSES.lock().unwrap();
+    let entry = processes.entry(sandbox.to_path_buf()).or_default();
+    entry.cancelled = false;
+    entry.timed_out.clear();
+    entry.stream_output = stream_output;
+}
+
+/// Sets the command timeouts of a sandbox: per node id, and for other nodes.
+pub(crate) fn set_timeouts(
+    sandbox: &Path,
+    default_timeout: Option<Duration>,
+    node_timeouts: HashMap<String, Duration>,
+) {
+    let mut processes = PROCESSES.lock().unwrap();
+    let entry = processes.entry(sandbox.to_path_buf()).or_default();
+    entry.default_timeout = default_timeout;
+    entry.node_timeouts = node_timeouts;
+}
+
+/// Returns true if the command of this node was terminated because of a timeout.
+pub fn is_timed_out(sandbox: &Path, node_id: &str) -> bool {
+    PROCESSES
+        .lock()
+        .unwrap()
+        .get(sandbox)
+        .is_some_and(|entry| entry.timed_out.contains(node_id))
+}
+
+/// Returns the exit code of the last command run for this node, if it exited normally.
+pub fn last_exit_code(sandbox: &Path, node_id: &str) -> Option<i32> {
+    PROCESSES
+        .lock()
+        .unwrap()
+        .get(sandbox)
+        .and_then(|entry| entry.exit_codes.get(node_id).copied())
+}
+
+/// Forgets the timeout and exit code of a node before a build attempt.
+pub(crate) fn begin_attempt(sandbox: &Path, node_id: &str) {
+    let mut processes = PROCESSES.lock().unwrap();
+    let entry = processes.entry(sandbox.to_path_buf()).or_default();
+    entry.timed_out.remove(node_id);
+    entry.exit_codes.remove(node_id);
+}
+
+/// Copies the log files of a node to `<node_id>.stdout.<attempt>` and
+/// `<node_id>.stderr.<attempt>`, so that every attempt is kept.
+pub(crate) fn keep_attempt_logs(sandbox: &Path, node_id: &str, attempt: u32) {
+    let Some((stdout_path, stderr_path)) = create_log_paths(sandbox, node_id) else {
+        return;
+    };
+    for path in [stdout_path, stderr_path] {
+        if path.exists() {
+            let mut attempt_path = path.clone().into_os_string();
+            attempt_path.push(format!(".{attempt}"));
+            if let Err(e) = fs::copy(&path, &attempt_path) {
+                log::error!("Failed to copy {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// Cancels the commands of every sandbox, as `cancel_commands` does for one.
+///
+/// Call it from the interrupt handler of the program: `make()` then stops,
+/// marks unbuilt nodes `Cancelled` and still writes `make-report.yml`.
+pub fn interrupt_all() {
+    warn!("interrupted, cancelling running commands");
+    let mut processes = PROCESSES.lock().unwrap();
+    for (sandbox, entry) in processes.iter_mut() {
+        entry.cancelled = true;
+        terminate_all(sandbox, entry);
+    }
+}
+
+/// Installs a Ctrl-C handler that calls [`interrupt_all`]; a second Ctrl-C
+/// exits immediately.
+///
+/// `make()` does not install it: the handler is global to the process, so a
+/// program using yamake as a library decides whether Ctrl-C is its own.
+pub fn install_interrupt_handler() -> Result<(), ctrlc::Error> {
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            std::process::exit(130);
+        }
+        interrupt_all();
+    })
+}
+
+/// Spawns the command in its own process group and registers its pid, unless the
+/// sandbox is cancelled. Returns the child and the timeout that applies to it.
+fn spawn_registered(
+    cmd: &mut Command,
+    sandbox: &Path,
+    node_id: &str,
+    stdin: Stdio,
+) -> Option<(Child, Option<Duration>)> {
+    let mut processes = PROCESSES.lock().unwrap();
+    let entry = processes.entry(sandbox.to_path_buf()).or_default();
+    if entry.cancelled {
+        info!("Cancelled, not running: {cmd:?}");
+        return None;
+    }
+    cmd.stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0);
+    match cmd.spawn() {
+        Ok(child) => {
+            entry.pids.insert(child.id(), None);
+            let timeout = entry
+                .node_timeouts
+                .get(node_id)
+                .copied()
+                .or(entry.default_timeout);
+            Some((child, timeout))
+        }
+        Err(e) => {
+            log::error!("Failed to execute command: {e}");
+            None
+        }
+    }
+}
+
+/// Where the live copy of a pipe goes, when output is streamed.
+#[derive(Clone, Copy)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Reads a pipe to the end in a background thread.
+///
+/// If `prefix` is set, each line is also written to the terminal as soon as it is
+/// complete, prefixed with `[prefix]`. Lines are written with a single call on the
+/// locked stream, so lines of parallel commands do not interleave.
+fn read_pipe<R: Read + Send + 'static>(
+    pipe: Option<R>,
+    prefix: Option<String>,
+    stream: Stream,
+) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let Some(pipe) = pipe else {
+            return buf;
+        };
+        let Some(prefix) = prefix else {
+            let mut pipe = pipe;
+            let _ = pipe.read_to_end(&mut buf);
+            return buf;
+        };
+        let mut reader = BufReader::new(pipe);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            buf.extend_from_slice(&line);
+            let mut out = format!("[{prefix}] ").into_bytes();
+            out.extend_from_slice(&line);
+            if !out.ends_with(b"\n") {
+                out.push(b'\n');
+            }
+            let _ = match stream {
+                Stream::Stdout => std::io::stdout().lock().write_all(&out),
+                Stream::Stderr => std::io::stderr().lock().write_all(&out),
+            };
+        }
+        buf
+    })
+}
+
+/// Waits for a registered child in a background thread, and sends its exit
+/// status once it is reaped.
+///
+/// The thread waits for the exit without reaping the child, then reaps and
+/// unregisters it while holding the lock, so that no signal is ever sent to a
+/// pid that was reused.
+fn wait_in_thread(mut child: Child, sandbox: &Path) -> mpsc::Receiver<std::io::Result<ExitStatus>> {
+    let (sender, receiver) = mpsc::channel();
+    let sandbox = sandbox.to_path_buf();
+    thread::spawn(move || {
+        let pid = child.id();
+        loop {
+            // SAFETY: waitid only writes to `info`; WNOWAIT leaves the child waitable.
+            let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+            let result = unsafe {
+                libc::waitid(
+                    libc::P_PID,
+                    pid as libc::id_t,
+                    &mut info,
+                    libc::WEXITED | libc::WNOWAIT,
+                )
+            };
+            if result == 0 || std::io::Error::last_os_error().kind() != ErrorKind::Interrupted {
+                break;
+            }
+        }
+        let mut processes = PROCESSES.lock().unwrap();
+        let status = child.wait();
+        if let Some(entry) = processes.get_mut(&sandbox) {
+            entry.pids.remove(&pid);
+        }
+        drop(processes);
+        let _ = sender.send(status);
+    });
+    receiver
+}
+
+/// Waits for a registered child, collecting its output.
+///
+/// If the timeout expires, the process group gets SIGTERM, then SIGKILL after a
+/// grace period; the output produced so far is still returned.
+fn wait_registered(
+    mut child: Child,
+    sandbox: &Path,
+    node_id: &str,
+    timeout: Option<Duration>,
+) -> std::io::Result<Output> {
+    let pid = child.id();
+    let stream_output = PROCESSES
+        .lock()
+        .unwrap()
+        .get(sandbox)
+        .is_some_and(|entry| entry.stream_output);
+    let prefix = stream_output.then(|| node_id.to_string());
+    let stdout_reader = read_pipe(child.stdout.take(), prefix.clone(), Stream::Stdout);
+    let stderr_reader = read_pipe(child.stderr.take(), prefix, Stream::Stderr);
+
+    let exited = wait_in_thread(child, sandbox);
+    let status = match timeout.map(|t| exited.recv_timeout(t)) {
+        Some(Err(RecvTimeoutError::Timeout)) => {
+            {
+                let mut processes = PROCESSES.lock().unwrap();
+                let entry = processes.entry(sandbox.to_path_buf()).or_default();
+                // Not reaped meanwhile, and not already cancelled
+                if entry.pids.get(&pid) == Some(&None) {
+                    warn!("timeout, terminating {node_id}");
+                    entry.timed_out.insert(node_id.to_string());
+                    terminate(sandbox, entry, pid);
+                }
+            }
+            exited.recv()
+        }
+        Some(Ok(status)) => Ok(status),
+        Some(Err(RecvTimeoutError::Disconnected)) | None => exited.recv(),
+    }
+    .map_err(|e| std::io::Error::other(e.to_string()))??;
+
+    Ok(Output {
+        status,
+        stdout: stdout_reader.join().unwrap_or_default(),
+        stderr: stderr_reader.join().unwrap_or_default(),
+    })
+}
 
 /// Creates the log directory structure and returns the stdout/stderr file paths.
 fn create_log_paths(
@@ -80,15 +442,62 @@ pub fn log_build(
 ///
 /// Log files are written to `<sandbox>/logs/<node_id>.stdout` and `<sandbox>/logs/<node_id>.stderr`.
 /// Returns true if the command succeeded, false otherwise.
+/// The command is not started, or is terminated, if the sandbox is cancelled
+/// (see [`cancel_commands`]). It is also terminated if it runs longer than the
+/// timeout of the node (see [`crate::model::GNode::timeout`]) or of the graph;
+/// the output captured so far is still written to the log files.
+/// If `G::stream_output` is set, the output is also copied to the terminal while
+/// the command runs, each line prefixed with `[node_id]`.
 pub fn run_command(cmd: &mut Command, sandbox: &Path, node_id: &str) -> bool {
+    run_registered(cmd, sandbox, node_id, Stdio::null())
+}
+
+/// Same as [`run_command`], with the content of the file `stdin` as standard input.
+pub fn run_command_with_stdin(
+    cmd: &mut Command,
+    sandbox: &Path,
+    node_id: &str,
+    stdin: &Path,
+) -> bool {
+    match File::open(stdin) {
+        Ok(file) => run_registered(cmd, sandbox, node_id, Stdio::from(file)),
+        Err(e) => {
+            log::error!("Failed to open {}: {e}", stdin.display());
+            false
+        }
+    }
+}
+
+/// Reads the standard output logged by the last command of a node, as bytes,
+/// without the command line written first.
+pub fn read_stdout_log(sandbox: &Path, node_id: &str) -> Option<Vec<u8>> {
+    let content = fs::read(sandbox.join("logs").join(format!("{node_id}.stdout"))).ok()?;
+    let start = content
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(content.len(), |i| i + 1);
+    Some(content[start..].to_vec())
+}
+
+fn run_registered(cmd: &mut Command, sandbox: &Path, node_id: &str, stdin: Stdio) -> bool {
     info!("Running: {cmd:?}");
 
     let Some((stdout_path, stderr_path)) = create_log_paths(sandbox, node_id) else {
         return false;
     };
 
-    match cmd.output() {
+    let Some((child, timeout)) = spawn_registered(cmd, sandbox, node_id, stdin) else {
+        return false;
+    };
+
+    match wait_registered(child, sandbox, node_id, timeout) {
         Ok(output) => {
+            if let Some(code) = output.status.code()
+                && let Some(entry) = PROCESSES.lock().unwrap().get_mut(sandbox)
+            {
+                entry.exit_codes.insert(node_id.to_string(), code);
+            }
+
             // Write stdout (with command as first line)
             if let Ok(mut file) = File::create(&stdout_path) {
                 let _ = writeln!(file, "{cmd:?}");
@@ -99,6 +508,9 @@ pub fn run_command(cmd: &mut Command, sandbox: &Path, node_id: &str) -> bool {
             if let Ok(mut file) = File::create(&stderr_path) {
                 let _ = writeln!(file, "{cmd:?}");
                 let _ = file.write_all(&output.stderr);
+                if let Some(timeout) = timeout.filter(|_| is_timed_out(sandbox, node_id)) {
+                    let _ = writeln!(file, "yamake: timed out after {timeout:?}");
+                }
             }
 
             output.status.success()
@@ -0,0 +1,74 @@
+use crate::model::{CompileCommand, CompileCommandsPaths, G, GNode};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+impl G {
+    /// Writes the compilation database of the graph (`compile_commands.json`).
+    ///
+    /// There is one entry per source file of each node that provides one
+    /// (`OFile`), with the arguments its build runs. Paths follow
+    /// `compile_commands_paths`. Call it after `make()` (or set
+    /// `compile_commands`) so that scanned and expanded nodes are included.
+    pub fn write_compile_commands(&self, path: &Path) -> io::Result<()> {
+        let mut entries: Vec<CompileCommand> = Vec::new();
+        for node_idx in self.g.node_indices() {
+            let predecessors: Vec<&(dyn GNode + Send + Sync)> = self
+                .predecessor_indices(node_idx)
+                .into_iter()
+                .map(|idx| self.g[idx].as_ref())
+                .collect();
+            entries.extend(self.g[node_idx].compile_commands(&self.sandbox, &predecessors));
+        }
+        entries.sort_by(|a, b| a.file.cmp(&b.file));
+
+        if self.compile_commands_paths == CompileCommandsPaths::Srcdir {
+            entries = entries
+                .into_iter()
+                .map(|entry| self.map_to_srcdir(entry))
+                .collect();
+        }
+
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(file, &entries).map_err(io::Error::other)
+    }
+
+    /// Maps the sources of an entry that were mounted from the source directory
+    /// back to it. Generated files keep their sandbox path, and each include
+    /// path of the sandbox is kept after its source directory, so that the
+    /// generated headers are still found.
+    fn map_to_srcdir(&self, entry: CompileCommand) -> CompileCommand {
+        let map = |path: &Path| -> Option<PathBuf> {
+            let relative = path.strip_prefix(&self.sandbox).ok()?;
+            Some(self.srcdir.join(relative))
+        };
+        let map_file = |path: &Path| map(path).filter(|source| source.is_file());
+
+        let mut arguments = Vec::new();
+        let mut args = entry.arguments.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "-I"
+                && let Some(dir) = args.next()
+            {
+                if let Some(source) = map(Path::new(&dir)).filter(|source| source.is_dir()) {
+                    arguments.push(arg.clone());
+                    arguments.push(source.to_string_lossy().to_string());
+                }
+                arguments.push(arg);
+                arguments.push(dir);
+                continue;
+            }
+            arguments.push(match map_file(Path::new(&arg)) {
+                Some(source) => source.to_string_lossy().to_string(),
+                None => arg,
+            });
+        }
+
+        CompileCommand {
+            directory: self.srcdir.clone(),
+            file: map_file(&entry.file).unwrap_or(entry.file),
+            arguments,
+            output: entry.output,
+        }
+    }
+}
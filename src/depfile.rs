@@ -0,0 +1,180 @@
+//! Parser for make-format depfiles.
+//!
+//! Many tools write the dependencies they discovered as a makefile fragment:
+//! `gcc -MD`, `clang -MD`, `latexmk -deps`, `sass`, `protoc --dependency_out`...
+//! This module parses them, and turns them into the result of [`GNode::scan`].
+//!
+//! # Examples
+//!
+//! ```
+//! use std::path::{Path, PathBuf};
+//! use yamake::depfile;
+//!
+//! let content = "out/main.o: src/main.c \\\n  src/my\\ header.h\n";
+//! let rules = depfile::parse(content);
+//! assert_eq!(rules.len(), 1);
+//! assert_eq!(rules[0].targets, vec![PathBuf::from("out/main.o")]);
+//! assert_eq!(
+//!     rules[0].prerequisites,
+//!     vec![PathBuf::from("src/main.c"), PathBuf::from("src/my header.h")]
+//! );
+//! ```
+//!
+//! [`GNode::scan`]: crate::model::GNode::scan
+
+use crate::paths::normalize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A rule of a depfile: `targets: prerequisites`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub targets: Vec<PathBuf>,
+    pub prerequisites: Vec<PathBuf>,
+}
+
+/// Parses a make-format depfile into its rules.
+///
+/// Line continuations (`\` before a newline), escaped spaces (`\ `), escaped
+/// `#`, `$$` and several targets per rule are handled, and comment lines are
+/// skipped. Lines without a `:` are ignored.
+pub fn parse(content: &str) -> Vec<Rule> {
+    let mut rules = Vec::new();
+    let joined = content.replace("\\\r\n", " ").replace("\\\n", " ");
+
+    for line in joined.lines() {
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut targets = Vec::new();
+        let mut prerequisites = Vec::new();
+        let mut after_colon = false;
+        for word in split_words(line) {
+            if after_colon {
+                prerequisites.push(PathBuf::from(word));
+            } else if word == ":" {
+                after_colon = true;
+            } else if let Some(target) = word.strip_suffix(':') {
+                targets.push(PathBuf::from(target));
+                after_colon = true;
+            } else if let Some((target, prerequisite)) = split_colon(&word) {
+                targets.push(PathBuf::from(target));
+                if !prerequisite.is_empty() {
+                    prerequisites.push(PathBuf::from(prerequisite));
+                }
+                after_colon = true;
+            } else {
+                targets.push(PathBuf::from(word));
+            }
+        }
+        if after_colon {
+            rules.push(Rule {
+                targets,
+                prerequisites,
+            });
+        }
+    }
+
+    rules
+}
+
+/// Returns the prerequisites of all rules, in order and without duplicates.
+pub fn prerequisites(content: &str) -> Vec<PathBuf> {
+    let mut result: Vec<PathBuf> = Vec::new();
+    for rule in parse(content) {
+        for path in rule.prerequisites {
+            if !result.contains(&path) {
+                result.push(path);
+            }
+        }
+    }
+    result
+}
+
+/// Turns the depfile at `sandbox/depfile` into the result of [`GNode::scan`].
+///
+/// Absolute prerequisites are made relative to the sandbox; those outside the
+/// sandbox (e.g. system headers) are dropped. Relative prerequisites are taken
+/// as relative to the sandbox. A missing depfile (the node was never built)
+/// gives a complete scan without dependencies, so that it does not block the build.
+///
+/// [`GNode::scan`]: crate::model::GNode::scan
+pub fn scan(sandbox: &Path, depfile: &Path) -> (bool, Vec<PathBuf>) {
+    let Ok(content) = fs::read_to_string(sandbox.join(depfile)) else {
+        return (true, Vec::new());
+    };
+    (true, relativize(sandbox, prerequisites(&content)))
+}
+
+/// Makes paths relative to the sandbox, dropping absolute paths outside of it.
+///
+/// `.` and `dir/..` are removed, so that the paths match the pathbufs of the
+/// nodes. A relative path starting with a relative sandbox is made relative
+/// to it; other relative paths are taken as relative to the sandbox.
+pub fn relativize(sandbox: &Path, paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    normalize_paths(sandbox, paths)
+        .into_iter()
+        .filter(|p| p.is_relative())
+        .collect()
+}
+
+/// Same as [`relativize`], but absolute paths outside of the sandbox (system
+/// or vendored headers) are kept, normalized.
+pub fn normalize_paths(sandbox: &Path, paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let sandbox = normalize(sandbox);
+    let mut result: Vec<PathBuf> = Vec::new();
+    for path in paths {
+        let path = normalize(&path);
+        let path = match path.strip_prefix(&sandbox) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => path,
+        };
+        if !result.contains(&path) {
+            result.push(path);
+        }
+    }
+    result
+}
+
+/// Splits `target:prerequisite` written without spaces, ignoring a Windows
+/// drive letter such as `C:`.
+fn split_colon(word: &str) -> Option<(&str, &str)> {
+    let search_from = if word.len() > 2 && word.as_bytes()[1] == b':' {
+        2
+    } else {
+        0
+    };
+    let pos = word[search_from..].find(':')? + search_from;
+    Some((&word[..pos], &word[pos + 1..]))
+}
+
+/// Splits a depfile line into words, unescaping `\ `, `\#` and `$$`.
+fn split_words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if matches!(chars.peek(), Some(' ') | Some('#')) => {
+                word.push(chars.next().unwrap());
+            }
+            '$' if chars.peek() == Some(&'$') => {
+                chars.next();
+                word.push('$');
+            }
+            ' ' | '\t' => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            _ => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
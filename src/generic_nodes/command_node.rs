@@ -0,0 +1,226 @@
+use crate::command::run_command;
+use crate::model::{G, GNode, GraphError};
+use log::error;
+use petgraph::graph::NodeIndex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A node built by a command given as an argument template, for one-off
+/// steps that do not deserve their own struct.
+///
+/// The first argument is the program. Placeholders, as in make:
+/// - `$@`: the output, i.e. the pathbuf of the node
+/// - `$<`: the first input, in the order of the edges (of `inputs` in a
+///   project file)
+/// - `$^`: all the inputs, `$^{Tag}` the inputs with this tag
+/// - `$$`: a literal `$` (`$$@` for the `$@` of a `sh -c` script)
+///
+/// Inputs are the pathbufs of the predecessors, sorted for `$^`, and all
+/// paths are relative to the sandbox, where the command runs. An argument
+/// that is only `$^` or `$^{Tag}` becomes one argument per input; inside a
+/// longer argument, the inputs are joined with spaces.
+///
+/// The recipe is the fingerprint of the node: changing it rebuilds the
+/// output. The node can be deserialized, e.g. from YAML:
+///
+/// ```yaml
+/// name: project/version.h
+/// tag: HFile
+/// recipe: [sh, -c, 'echo "#define VERSION \"$$(cat $$1)\"" > $$0', $@, $<]
+/// ```
+///
+/// A project file of such nodes, with their inputs, is added to a graph by
+/// [`load_commands`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandNode {
+    pub name: String,
+    /// The tag of the output, that successors filter on (`CFile`, `HFile`...).
+    pub tag: String,
+    pub recipe: Vec<String>,
+}
+
+impl CommandNode {
+    pub fn new(name: &str, tag: &str, recipe: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            tag: tag.to_string(),
+            recipe: recipe.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    /// The recipe with its placeholders replaced, program first.
+    pub fn arguments(
+        &self,
+        predecessors: &[&(dyn GNode + Send + Sync)],
+    ) -> Result<Vec<String>, String> {
+        let mut inputs: Vec<(String, String)> = predecessors
+            .iter()
+            .map(|p| (p.pathbuf().to_string_lossy().to_string(), p.tag()))
+            .collect();
+        let first = inputs.first().map(|(path, _)| path.clone());
+        inputs.sort();
+
+        let mut result = Vec::new();
+        for arg in &self.recipe {
+            let mut parts: Vec<Part> = Vec::new();
+            let mut rest = arg.as_str();
+            while let Some(pos) = rest.find('$') {
+                parts.push(Part::Text(rest[..pos].to_string()));
+                rest = &rest[pos + 1..];
+                let (part, len) = match rest.chars().next() {
+                    Some('$') => (Part::Text("$".to_string()), 1),
+                    Some('@') => (Part::Text(self.name.clone()), 1),
+                    Some('<') => match &first {
+                        Some(path) => (Part::Text(path.clone()), 1),
+                        None => return Err(format!("{arg}: $< without input")),
+                    },
+                    Some('^') => match rest[1..].strip_prefix('{') {
+                        Some(tagged) => {
+                            let end = tagged
+                                .find('}')
+                                .ok_or_else(|| format!("{arg}: unterminated $^{{"))?;
+                            let tag = &tagged[..end];
+                            let paths = inputs
+                                .iter()
+                                .filter(|(_, t)| t == tag)
+                                .map(|(p, _)| p.clone())
+                                .collect();
+                            (Part::List(paths), end + 3)
+                        }
+                        None => (
+                            Part::List(inputs.iter().map(|(p, _)| p.clone()).collect()),
+                            1,
+                        ),
+                    },
+                    _ => return Err(format!("{arg}: unknown placeholder")),
+                };
+                parts.push(part);
+                rest = &rest[len..];
+            }
+            parts.push(Part::Text(rest.to_string()));
+            parts.retain(|p| !matches!(p, Part::Text(t) if t.is_empty()));
+
+            match parts.as_slice() {
+                [Part::List(paths)] => result.extend(paths.iter().cloned()),
+                _ => result.push(
+                    parts
+                        .iter()
+                        .map(|p| match p {
+                            Part::Text(t) => t.clone(),
+                            Part::List(paths) => paths.join(" "),
+                        })
+                        .collect(),
+                ),
+            }
+        }
+        if result.is_empty() {
+            return Err("empty recipe".to_string());
+        }
+        Ok(result)
+    }
+}
+
+/// A piece of an argument: text, or a list of inputs.
+enum Part {
+    Text(String),
+    List(Vec<String>),
+}
+
+impl GNode for CommandNode {
+    fn build(&self, sandbox: &Path, predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
+        let arguments = match self.arguments(predecessors) {
+            Ok(arguments) => arguments,
+            Err(e) => {
+                error!("invalid recipe for {}: {e}", self.name);
+                return false;
+            }
+        };
+        let mut cmd = Command::new(&arguments[0]);
+        cmd.current_dir(sandbox).args(&arguments[1..]);
+        run_command(&mut cmd, sandbox, &self.name)
+    }
+
+    fn fingerprint(&self, _predecessors: &[&(dyn GNode + Send + Sync)]) -> Option<String> {
+        Some(format!("{:?}", self.recipe))
+    }
+
+    fn tag(&self) -> String {
+        self.tag.clone()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
+
+/// A `CommandNode` of a project file, with the pathbufs of its inputs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandRule {
+    #[serde(flatten)]
+    pub node: CommandNode,
+    /// Pathbufs of the predecessors: nodes already in the graph, or rules
+    /// earlier in the file.
+    #[serde(default)]
+    pub inputs: Vec<PathBuf>,
+}
+
+/// Adds the rules of a project file to the graph, with an edge from each of
+/// their inputs, and returns the indices of the new nodes. The whole file is
+/// checked first: on an error, nothing is added. The file is JSON if
+/// its extension is `.json`, YAML otherwise, and holds a list of rules:
+///
+/// ```yaml
+/// - name: project/version.h
+///   tag: HFile
+///   recipe: [sh, -c, 'echo "#define VERSION \"$$(cat $$1)\"" > $$0', $@, $<]
+///   inputs: [project/version.txt]
+/// ```
+pub fn load_commands(g: &mut G, path: &Path) -> Result<Vec<NodeIndex>, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    let rules: Vec<CommandRule> = if path.extension().is_some_and(|e| e == "json") {
+        serde_json::from_str(&content)
+            .map_err(|e| format!("cannot parse {}: {e}", path.display()))?
+    } else {
+        serde_yaml::from_str(&content)
+            .map_err(|e| format!("cannot parse {}: {e}", path.display()))?
+    };
+
+    // Checked before adding anything, so that an error leaves the graph as it was
+    let mut names: Vec<PathBuf> = Vec::new();
+    for rule in &rules {
+        let name = rule.node.pathbuf();
+        if let Some(input) = rule
+            .inputs
+            .iter()
+            .find(|input| g.node_index(input).is_none() && !names.contains(input))
+        {
+            return Err(format!(
+                "{}: unknown input {}",
+                rule.node.name,
+                input.display()
+            ));
+        }
+        if g.node_index(&name).is_some() || names.contains(&name) {
+            return Err(GraphError::DuplicatePathBuf(name).to_string());
+        }
+        names.push(name);
+    }
+
+    let mut indices = Vec::new();
+    for rule in rules {
+        let inputs: Vec<NodeIndex> = rule
+            .inputs
+            .iter()
+            .filter_map(|input| g.node_index(input))
+            .collect();
+        let idx = g.add_node(rule.node).map_err(|e| e.to_string())?;
+        for input in inputs {
+            g.add_edge(input, idx);
+        }
+        indices.push(idx);
+    }
+    Ok(indices)
+}
@@ -0,0 +1,25 @@
+use crate::model::GRootNode;
+use std::path::PathBuf;
+
+/// The data of a template: JSON if its extension is `.json`, YAML otherwise.
+pub struct DataFile {
+    pub name: String,
+}
+
+impl DataFile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+        }
+    }
+}
+
+impl GRootNode for DataFile {
+    fn tag(&self) -> String {
+        "DataFile".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
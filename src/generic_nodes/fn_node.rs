@@ -0,0 +1,238 @@
+use crate::command::log_build;
+use crate::model::{ExpandResult, GNode, GRootNode};
+use log::{error, info};
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+
+type BuildFn = dyn Fn(&FnContext) -> Result<(), String> + Send + Sync;
+type ScanFn = dyn Fn(&FnContext) -> (bool, Vec<PathBuf>) + Send + Sync;
+type ExpandFn = dyn Fn(&FnContext) -> ExpandResult + Send + Sync;
+
+/// What the closures of [`FnNode`] and [`FnRootNode`] get: the sandbox, the
+/// predecessors by tag, and a logger.
+pub struct FnContext<'a> {
+    pub sandbox: &'a Path,
+    /// The pathbuf of the node, also its id for the logs.
+    pub name: &'a str,
+    pub predecessors: &'a [&'a (dyn GNode + Send + Sync)],
+    stdout: RefCell<String>,
+    stderr: RefCell<String>,
+}
+
+impl<'a> FnContext<'a> {
+    fn new(
+        sandbox: &'a Path,
+        name: &'a str,
+        predecessors: &'a [&'a (dyn GNode + Send + Sync)],
+    ) -> Self {
+        Self {
+            sandbox,
+            name,
+            predecessors,
+            stdout: RefCell::new(String::new()),
+            stderr: RefCell::new(String::new()),
+        }
+    }
+
+    /// The output of the node, in the sandbox.
+    pub fn output(&self) -> PathBuf {
+        self.sandbox.join(self.name)
+    }
+
+    /// The predecessors with this tag, in the sandbox, sorted.
+    pub fn inputs(&self, tag: &str) -> Vec<PathBuf> {
+        let mut inputs: Vec<PathBuf> = self
+            .predecessors
+            .iter()
+            .filter(|p| p.tag() == tag)
+            .map(|p| self.sandbox.join(p.pathbuf()))
+            .collect();
+        inputs.sort();
+        inputs
+    }
+
+    /// The only predecessor with this tag, in the sandbox.
+    pub fn input(&self, tag: &str) -> Result<PathBuf, String> {
+        match self.inputs(tag).as_slice() {
+            [input] => Ok(input.clone()),
+            inputs => Err(format!(
+                "{}: expected one {tag} predecessor, found {}",
+                self.name,
+                inputs.len()
+            )),
+        }
+    }
+
+    /// Writes a line to the stdout log of the node (for a build).
+    pub fn log(&self, line: &str) {
+        info!("{}: {line}", self.name);
+        let mut stdout = self.stdout.borrow_mut();
+        stdout.push_str(line);
+        stdout.push('\n');
+    }
+
+    /// Writes a line to the stderr log of the node (for a build).
+    pub fn log_error(&self, line: &str) {
+        error!("{}: {line}", self.name);
+        let mut stderr = self.stderr.borrow_mut();
+        stderr.push_str(line);
+        stderr.push('\n');
+    }
+}
+
+/// A node built by a closure, for a quick in-process step:
+///
+/// ```
+/// use std::fs;
+/// use yamake::generic_nodes::FnNode;
+///
+/// let count = FnNode::new("p/count.txt", "Count", |ctx| {
+///     let words = fs::read_to_string(ctx.input("TextFile")?).map_err(|e| e.to_string())?;
+///     let count = words.split_whitespace().count();
+///     ctx.log(&format!("{count} words"));
+///     fs::write(ctx.output(), count.to_string()).map_err(|e| e.to_string())
+/// });
+/// ```
+///
+/// The lines logged with the context, and the error returned by the build,
+/// are written to the logs of the node. Scan and expand closures are optional.
+/// A closure has no settings that `make()` can compare: change the
+/// fingerprint (`with_fingerprint`) when it changes, to rebuild the output.
+pub struct FnNode {
+    pub name: String,
+    pub tag: String,
+    pub fingerprint: Option<String>,
+    build: Box<BuildFn>,
+    scan: Option<Box<ScanFn>>,
+    expand: Option<Box<ExpandFn>>,
+}
+
+impl FnNode {
+    pub fn new(
+        name: &str,
+        tag: &str,
+        build: impl Fn(&FnContext) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            tag: tag.to_string(),
+            fingerprint: None,
+            build: Box::new(build),
+            scan: None,
+            expand: None,
+        }
+    }
+
+    pub fn with_scan(
+        mut self,
+        scan: impl Fn(&FnContext) -> (bool, Vec<PathBuf>) + Send + Sync + 'static,
+    ) -> Self {
+        self.scan = Some(Box::new(scan));
+        self
+    }
+
+    pub fn with_expand(
+        mut self,
+        expand: impl Fn(&FnContext) -> ExpandResult + Send + Sync + 'static,
+    ) -> Self {
+        self.expand = Some(Box::new(expand));
+        self
+    }
+
+    /// A version of the closure, e.g. `"v2"`.
+    pub fn with_fingerprint(mut self, fingerprint: &str) -> Self {
+        self.fingerprint = Some(fingerprint.to_string());
+        self
+    }
+}
+
+impl GNode for FnNode {
+    fn build(&self, sandbox: &Path, predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
+        let ctx = FnContext::new(sandbox, &self.name, predecessors);
+        let result = (self.build)(&ctx);
+        if let Err(e) = &result {
+            ctx.log_error(e);
+        }
+        log_build(
+            sandbox,
+            &self.name,
+            &format!("FnNode {}", self.name),
+            &ctx.stdout.borrow(),
+            &ctx.stderr.borrow(),
+        );
+        result.is_ok()
+    }
+
+    fn scan(
+        &self,
+        sandbox: &Path,
+        predecessors: &[&(dyn GNode + Send + Sync)],
+    ) -> (bool, Vec<PathBuf>) {
+        match &self.scan {
+            Some(scan) => scan(&FnContext::new(sandbox, &self.name, predecessors)),
+            None => (true, Vec::new()),
+        }
+    }
+
+    fn expand(&self, sandbox: &Path, predecessors: &[&(dyn GNode + Send + Sync)]) -> ExpandResult {
+        match &self.expand {
+            Some(expand) => expand(&FnContext::new(sandbox, &self.name, predecessors)),
+            None => Ok((Vec::new(), Vec::new())),
+        }
+    }
+
+    fn fingerprint(&self, _predecessors: &[&(dyn GNode + Send + Sync)]) -> Option<String> {
+        self.fingerprint.clone()
+    }
+
+    fn tag(&self) -> String {
+        self.tag.clone()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
+
+/// A root node with an optional expand closure, e.g. a configuration file
+/// that adds nodes to the graph.
+pub struct FnRootNode {
+    pub name: String,
+    pub tag: String,
+    expand: Option<Box<ExpandFn>>,
+}
+
+impl FnRootNode {
+    pub fn new(name: &str, tag: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            tag: tag.to_string(),
+            expand: None,
+        }
+    }
+
+    pub fn with_expand(
+        mut self,
+        expand: impl Fn(&FnContext) -> ExpandResult + Send + Sync + 'static,
+    ) -> Self {
+        self.expand = Some(Box::new(expand));
+        self
+    }
+}
+
+impl GRootNode for FnRootNode {
+    fn expand(&self, sandbox: &Path, predecessors: &[&(dyn GNode + Send + Sync)]) -> ExpandResult {
+        match &self.expand {
+            Some(expand) => expand(&FnContext::new(sandbox, &self.name, predecessors)),
+            None => Ok((Vec::new(), Vec::new())),
+        }
+    }
+
+    fn tag(&self) -> String {
+        self.tag.clone()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
@@ -0,0 +1,13 @@
+pub mod command_node;
+pub mod data_file;
+pub mod fn_node;
+pub mod template_file;
+pub mod template_list;
+pub mod template_node;
+
+pub use command_node::{CommandNode, CommandRule, load_commands};
+pub use data_file::DataFile;
+pub use fn_node::{FnContext, FnNode, FnRootNode};
+pub use template_file::TemplateFile;
+pub use template_list::TemplateList;
+pub use template_node::TemplateNode;
@@ -0,0 +1,26 @@
+use crate::model::GRootNode;
+use std::path::PathBuf;
+
+/// A template, rendered with minijinja (Jinja2 syntax) by a `TemplateNode` or a
+/// `TemplateList`.
+pub struct TemplateFile {
+    pub name: String,
+}
+
+impl TemplateFile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+        }
+    }
+}
+
+impl GRootNode for TemplateFile {
+    fn tag(&self) -> String {
+        "TemplateFile".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
@@ -0,0 +1,230 @@
+use super::template_node::{data_context, load_data, predecessor, render};
+use crate::c_nodes::{AFile, CFile, CppFile, HFile, HppFile, OFile};
+use crate::command::log_build;
+use crate::model::{Edge, ExpandError, ExpandResult, GNode};
+use log::error;
+use minijinja::value::ValueKind;
+use minijinja::{Value, context};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Renders its `TemplateFile` predecessor once per entry of a list of its
+/// `DataFile` predecessor, then adds the generated sources to the graph.
+///
+/// The list is the data itself, or the value of the key `list`. For each
+/// entry, the template and the `output` path template see the entry as
+/// `item`, its position as `index` and, for a mapping, its key as `key`, in
+/// addition to the variables of a `TemplateNode`. With `languages.yml`:
+/// `output` is `project_expand/generated/{{ item.language }}.c`.
+///
+/// The node is the list of the generated files, one per line. Its expand adds
+/// a `CFile` (`CppFile`) and an `OFile` for each generated source, compiled
+/// with the include paths and flags of `with_include_paths` and
+/// `with_compile_flags`, an `HFile` (`HppFile`) for each generated header,
+/// and, with `with_archive`, the `AFile` that the objects are archived into.
+pub struct TemplateList {
+    pub name: String,
+    /// The path of each generated file, a template.
+    pub output: String,
+    /// The key of the list in the data, if the data is not the list.
+    pub list: Option<String>,
+    pub archive: Option<String>,
+    /// The include paths of the objects of the generated sources.
+    pub include_paths: Vec<PathBuf>,
+    /// The compile flags of the objects of the generated sources.
+    pub compile_flags: Vec<String>,
+}
+
+impl TemplateList {
+    pub fn new(name: &str, output: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            output: output.to_string(),
+            list: None,
+            archive: None,
+            include_paths: Vec::new(),
+            compile_flags: Vec::new(),
+        }
+    }
+
+    pub fn with_list(mut self, key: &str) -> Self {
+        self.list = Some(key.to_string());
+        self
+    }
+
+    /// Archives the objects of the generated sources into this `AFile`. When
+    /// the graph has it before the expand (to link it), add an edge from the
+    /// list to it, so that it is not mounted as a root node.
+    pub fn with_archive(mut self, archive: &str) -> Self {
+        self.archive = Some(archive.to_string());
+        self
+    }
+
+    /// Compiles the generated sources with these include paths, relative to
+    /// the sandbox.
+    pub fn with_include_paths(mut self, include_paths: Vec<PathBuf>) -> Self {
+        self.include_paths = include_paths;
+        self
+    }
+
+    /// Compiles the generated sources with these flags.
+    pub fn with_compile_flags(mut self, compile_flags: Vec<String>) -> Self {
+        self.compile_flags = compile_flags;
+        self
+    }
+
+    /// Renders the files, and returns their paths relative to the sandbox.
+    fn render_all(
+        &self,
+        sandbox: &Path,
+        predecessors: &[&(dyn GNode + Send + Sync)],
+    ) -> Result<Vec<String>, String> {
+        let template = predecessor(sandbox, predecessors, "TemplateFile")?;
+        let source = fs::read_to_string(&template)
+            .map_err(|e| format!("cannot read {}: {e}", template.display()))?;
+        let data = load_data(&predecessor(sandbox, predecessors, "DataFile")?)?;
+        let list = match &self.list {
+            Some(key) => data.get_attr(key).map_err(|e| e.to_string())?,
+            None => data.clone(),
+        };
+        let base = data_context(data);
+
+        let entries: Vec<Value> = match list.kind() {
+            ValueKind::Seq => list
+                .try_iter()
+                .map_err(|e| e.to_string())?
+                .enumerate()
+                .map(|(index, item)| context! { item, index, ..base.clone() })
+                .collect(),
+            ValueKind::Map => list
+                .try_iter()
+                .map_err(|e| e.to_string())?
+                .enumerate()
+                .map(|(index, key)| {
+                    let item = list.get_item(&key).unwrap_or_default();
+                    context! { key, item, index, ..base.clone() }
+                })
+                .collect(),
+            kind => return Err(format!("expected a list, found {kind}")),
+        };
+
+        let mut outputs = Vec::new();
+        for ctx in entries {
+            let output = render(&self.output, ctx.clone())?;
+            let path = sandbox.join(&output);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("cannot create {}: {e}", parent.display()))?;
+            }
+            fs::write(&path, render(&source, ctx)?)
+                .map_err(|e| format!("cannot write {output}: {e}"))?;
+            outputs.push(output);
+        }
+        Ok(outputs)
+    }
+}
+
+/// The root node type of a generated file, by extension.
+fn generated_node(path: &str) -> Option<Box<dyn GNode + Send + Sync>> {
+    match Path::new(path).extension()?.to_str()? {
+        "c" => Some(Box::new(CFile::new(path))),
+        "cpp" | "cc" | "cxx" => Some(Box::new(CppFile::new(path))),
+        "h" => Some(Box::new(HFile::new(path))),
+        "hpp" => Some(Box::new(HppFile::new(path))),
+        _ => None,
+    }
+}
+
+impl GNode for TemplateList {
+    fn build(&self, sandbox: &Path, predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
+        let description = format!("Render template list: {}", self.name);
+        let result = self.render_all(sandbox, predecessors).and_then(|outputs| {
+            let manifest: String = outputs.iter().map(|o| format!("{o}\n")).collect();
+            fs::write(sandbox.join(&self.name), &manifest)
+                .map_err(|e| format!("cannot write {}: {e}", self.name))?;
+            Ok(manifest)
+        });
+        match result {
+            Ok(manifest) => log_build(sandbox, &self.name, &description, &manifest, ""),
+            Err(e) => {
+                error!("{}: {e}", self.name);
+                log_build(sandbox, &self.name, &description, "", &format!("{e}\n"));
+                false
+            }
+        }
+    }
+
+    fn expand(&self, sandbox: &Path, _predecessors: &[&(dyn GNode + Send + Sync)]) -> ExpandResult {
+        let manifest_path = sandbox.join(&self.name);
+        let manifest = fs::read_to_string(&manifest_path)
+            .map_err(|e| ExpandError::ReadError(manifest_path.clone(), e))?;
+
+        let mut nodes: Vec<Box<dyn GNode + Send + Sync>> = Vec::new();
+        let mut edges: Vec<Edge> = Vec::new();
+        let this = || Box::new(TemplateList::new(&self.name, &self.output));
+
+        for output in manifest.lines() {
+            // Generated files are successors of the list, not root nodes to mount
+            let Some(file) = generated_node(output) else {
+                return Err(ExpandError::ParseError(format!(
+                    "{}: no node for the generated file {output}",
+                    self.name
+                )));
+            };
+            let tag = file.tag();
+            nodes.push(file);
+            edges.push(Edge {
+                nfrom: this(),
+                nto: generated_node(output).unwrap(),
+            });
+
+            // An object for each source, archived if asked
+            let cxx = match tag.as_str() {
+                "CFile" => false,
+                "CppFile" => true,
+                _ => continue,
+            };
+            let o_path = PathBuf::from(output).with_extension("o");
+            let o_path = o_path.to_string_lossy();
+            let object = || -> Box<dyn GNode + Send + Sync> {
+                let (include_paths, flags) =
+                    (self.include_paths.clone(), self.compile_flags.clone());
+                if cxx {
+                    Box::new(OFile::new_cxx(&o_path, include_paths, flags))
+                } else {
+                    Box::new(OFile::new(&o_path, include_paths, flags))
+                }
+            };
+            nodes.push(object());
+            edges.push(Edge {
+                nfrom: generated_node(output).unwrap(),
+                nto: object(),
+            });
+            if let Some(archive) = &self.archive {
+                edges.push(Edge {
+                    nfrom: object(),
+                    nto: Box::new(AFile::new(archive)),
+                });
+            }
+        }
+        if let Some(archive) = &self.archive {
+            nodes.push(Box::new(AFile::new(archive)));
+        }
+        Ok((nodes, edges))
+    }
+
+    fn fingerprint(&self, _predecessors: &[&(dyn GNode + Send + Sync)]) -> Option<String> {
+        Some(format!(
+            "{} {:?} {:?} {:?} {:?}",
+            self.output, self.list, self.archive, self.include_paths, self.compile_flags
+        ))
+    }
+
+    fn tag(&self) -> String {
+        "TemplateList".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
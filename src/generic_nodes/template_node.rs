@@ -0,0 +1,120 @@
+use crate::command::log_build;
+use crate::model::GNode;
+use log::error;
+use minijinja::value::ValueKind;
+use minijinja::{Environment, UndefinedBehavior, Value, context};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Reads a `DataFile`: JSON if its extension is `.json`, YAML otherwise.
+pub(crate) fn load_data(path: &Path) -> Result<Value, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    if path.extension().is_some_and(|e| e == "json") {
+        let data: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|e| format!("cannot parse {}: {e}", path.display()))?;
+        Ok(Value::from_serialize(&data))
+    } else {
+        let data: serde_yaml::Value = serde_yaml::from_str(&content)
+            .map_err(|e| format!("cannot parse {}: {e}", path.display()))?;
+        Ok(Value::from_serialize(&data))
+    }
+}
+
+/// Renders a template. An undefined variable is an error, not an empty string.
+pub(crate) fn render(source: &str, ctx: Value) -> Result<String, String> {
+    let mut env = Environment::new();
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    env.render_str(source, ctx).map_err(|e| e.to_string())
+}
+
+/// The context of a template: `data`, and the keys of a mapping.
+pub(crate) fn data_context(data: Value) -> Value {
+    if data.kind() == ValueKind::Map {
+        context! { data => data.clone(), ..data }
+    } else {
+        context! { data => data }
+    }
+}
+
+/// The only predecessor with this tag, in the sandbox.
+pub(crate) fn predecessor(
+    sandbox: &Path,
+    predecessors: &[&(dyn GNode + Send + Sync)],
+    tag: &str,
+) -> Result<PathBuf, String> {
+    match predecessors
+        .iter()
+        .filter(|p| p.tag() == tag)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [p] => Ok(sandbox.join(p.pathbuf())),
+        found => Err(format!(
+            "expected one {tag} predecessor, found {}",
+            found.len()
+        )),
+    }
+}
+
+/// A file rendered from its `TemplateFile` predecessor, with the data of its
+/// `DataFile` predecessor.
+///
+/// The template sees the data as `data` and, if it is a mapping, each of its
+/// keys as a variable: with `greetings.yml`, `{% for code, l in languages|items %}`.
+/// The tag of the output is given, e.g. `CFile` for a generated source.
+pub struct TemplateNode {
+    pub name: String,
+    pub tag: String,
+}
+
+impl TemplateNode {
+    pub fn new(name: &str, tag: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            tag: tag.to_string(),
+        }
+    }
+
+    fn render(
+        &self,
+        sandbox: &Path,
+        predecessors: &[&(dyn GNode + Send + Sync)],
+    ) -> Result<String, String> {
+        let template = predecessor(sandbox, predecessors, "TemplateFile")?;
+        let source = fs::read_to_string(&template)
+            .map_err(|e| format!("cannot read {}: {e}", template.display()))?;
+        let data = load_data(&predecessor(sandbox, predecessors, "DataFile")?)?;
+        render(&source, data_context(data))
+    }
+}
+
+impl GNode for TemplateNode {
+    fn build(&self, sandbox: &Path, predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
+        let description = format!("Render template: {}", self.name);
+        let result = self.render(sandbox, predecessors).and_then(|content| {
+            fs::write(sandbox.join(&self.name), content)
+                .map_err(|e| format!("cannot write {}: {e}", self.name))
+        });
+        match result {
+            Ok(()) => log_build(sandbox, &self.name, &description, "", ""),
+            Err(e) => {
+                error!("{}: {e}", self.name);
+                log_build(sandbox, &self.name, &description, "", &format!("{e}\n"));
+                false
+            }
+        }
+    }
+
+    fn fingerprint(&self, _predecessors: &[&(dyn GNode + Send + Sync)]) -> Option<String> {
+        Some(self.tag.clone())
+    }
+
+    fn tag(&self) -> String {
+        self.tag.clone()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
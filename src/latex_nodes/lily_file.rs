@@ -0,0 +1,24 @@
+use crate::model::GRootNode;
+use std::path::PathBuf;
+
+pub struct LilyFile {
+    pub name: String,
+}
+
+impl LilyFile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+        }
+    }
+}
+
+impl GRootNode for LilyFile {
+    fn tag(&self) -> String {
+        "LilyFile".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
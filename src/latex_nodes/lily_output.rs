@@ -0,0 +1,136 @@
+use crate::command::run_command;
+use crate::model::GNode;
+use crate::paths::normalize;
+use log::{info, warn};
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The output directory of a LilyPond score, to be included in a LaTeX document.
+///
+/// For `project_latex/solo.output`, runs `lilypond` on `project_latex/solo.ly`
+/// with the EPS backend and `--pdf`, which writes one PDF per system and
+/// `solo-systems.tex` into `project_latex/solo.output/`. It then writes
+/// `solo.output/solo.tex`, so that the document includes the score with
+/// `\subimport{solo.output}{solo}`.
+///
+/// The node is the directory, declared with `output_is_directory`: it is
+/// emptied before each run, and a `PdfFile` depending on it (with an explicit
+/// edge) is rebuilt when its content changes.
+///
+/// `\include "file.ly"` is relative to the including file. An include that is
+/// not in the sandbox, like `english.ly`, is bundled with lilypond and not
+/// tracked; a generated one needs an explicit edge to this node.
+pub struct LilyOutput {
+    pub name: String,
+    pub program: String,
+}
+
+impl LilyOutput {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            program: "lilypond".to_string(),
+        }
+    }
+
+    /// Uses another `lilypond` executable.
+    pub fn with_program(mut self, program: &str) -> Self {
+        self.program = program.to_string();
+        self
+    }
+
+    /// The score, relative to the sandbox: `solo.ly` for `solo.output`.
+    pub fn source(&self) -> PathBuf {
+        PathBuf::from(&self.name).with_extension("ly")
+    }
+}
+
+impl GNode for LilyOutput {
+    fn build(&self, sandbox: &Path, _predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
+        let source = self.source();
+        let Some(stem) = source.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+            return false;
+        };
+        let output_dir = sandbox.join(&self.name);
+        if let Err(e) = fs::create_dir_all(&output_dir) {
+            warn!("cannot create {}: {e}", output_dir.display());
+            return false;
+        }
+
+        let mut cmd = Command::new(&self.program);
+        cmd.current_dir(sandbox.join(source.parent().unwrap_or(Path::new(""))))
+            .arg("-dbackend=eps")
+            .arg("-drelative-includes")
+            .arg("--pdf")
+            .arg("-o")
+            .arg(output_dir.join(&stem))
+            .arg(sandbox.join(&source));
+        if !run_command(&mut cmd, sandbox, &self.name) {
+            return false;
+        }
+
+        // Entry point for \subimport{<stem>.output}{<stem>}
+        fs::write(
+            output_dir.join(format!("{stem}.tex")),
+            format!("\\input{{{stem}-systems.tex}}\n"),
+        )
+        .is_ok()
+    }
+
+    fn scan(
+        &self,
+        sandbox: &Path,
+        predecessors: &[&(dyn GNode + Send + Sync)],
+    ) -> (bool, Vec<PathBuf>) {
+        info!("scan {}", self.pathbuf().display());
+        // \include "file.ly", relative to the directory of the including file
+        let include_re = Regex::new(r#"\\include\s+"([^"]+)""#).unwrap();
+        let mut result: Vec<PathBuf> = Vec::new();
+        let mut scan_complete = true;
+        let mut to_scan = vec![self.source()];
+        while let Some(file) = to_scan.pop() {
+            let Ok(content) = fs::read_to_string(sandbox.join(&file)) else {
+                warn!("while scanning, file not found: {}", file.display());
+                scan_complete = false;
+                continue;
+            };
+            let dir = file.parent().unwrap_or(Path::new(""));
+            for cap in include_re.captures_iter(&content) {
+                let path = normalize(&dir.join(&cap[1]));
+                if result.contains(&path) {
+                    continue;
+                }
+                if sandbox.join(&path).exists() {
+                    to_scan.push(path.clone());
+                } else if predecessors.iter().any(|p| p.pathbuf() == path) {
+                    // Generated, not built yet: scanned on the next iteration
+                    scan_complete = false;
+                } else {
+                    // Found by lilypond in its own directories, like english.ly
+                    info!("external include: {}", &cap[1]);
+                    continue;
+                }
+                result.push(path);
+            }
+        }
+        (scan_complete, result)
+    }
+
+    fn fingerprint(&self, _predecessors: &[&(dyn GNode + Send + Sync)]) -> Option<String> {
+        Some(self.program.clone())
+    }
+
+    fn output_is_directory(&self) -> bool {
+        true
+    }
+
+    fn tag(&self) -> String {
+        "LilyOutput".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
@@ -0,0 +1,11 @@
+pub mod lily_file;
+pub mod lily_output;
+pub mod pdf_file;
+pub mod tex_file;
+pub mod tikz_file;
+
+pub use lily_file::LilyFile;
+pub use lily_output::LilyOutput;
+pub use pdf_file::PdfFile;
+pub use tex_file::TexFile;
+pub use tikz_file::TikzFile;
@@ -0,0 +1,242 @@
+use crate::command::run_command;
+use crate::model::GNode;
+use crate::paths::normalize;
+use log::{info, warn};
+use regex::Regex;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Extensions tried, in the order of pdflatex and lualatex, for an
+/// `\includegraphics` without extension.
+const GRAPHICS_EXTENSIONS: [&str; 5] = ["pdf", "png", "jpg", "jpeg", "eps"];
+
+/// A PDF document, built from the `.tex` file with the same name.
+///
+/// The engine runs in the directory of the main file, until the `.aux` file
+/// does not change anymore (cross references, table of contents), at most
+/// `max_runs` times. The scan follows `\input`, `\include`, `\import`,
+/// `\subimport` and `\includegraphics`; arguments using macro parameters
+/// (`\subimport{#1.output}{#1}`) cannot be resolved and are ignored.
+pub struct PdfFile {
+    pub name: String,
+    /// `lualatex` (default) or `xelatex`: both support `fontspec`.
+    pub engine: String,
+    pub max_runs: u32,
+}
+
+impl PdfFile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            engine: "lualatex".to_string(),
+            max_runs: 5,
+        }
+    }
+
+    /// Uses another engine, e.g. `xelatex`.
+    pub fn with_engine(mut self, engine: &str) -> Self {
+        self.engine = engine.to_string();
+        self
+    }
+
+    /// The main source file, relative to the sandbox.
+    pub fn main_tex(&self) -> PathBuf {
+        PathBuf::from(&self.name).with_extension("tex")
+    }
+}
+
+/// Removes the comment of each line: from the first `%` that is not `\%`.
+fn strip_comments(content: &str) -> String {
+    content
+        .lines()
+        .map(|line| {
+            let mut escaped = false;
+            for (i, c) in line.char_indices() {
+                match c {
+                    '%' if !escaped => return &line[..i],
+                    '\\' => escaped = !escaped,
+                    _ => escaped = false,
+                }
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A file included with `\input` or `\include`: `.tex` is added if there is no extension.
+fn tex_path(base: &Path, arg: &str, force_tex: bool) -> PathBuf {
+    let path = normalize(&base.join(arg));
+    if force_tex || path.extension().is_none() {
+        let mut name = path.into_os_string();
+        name.push(".tex");
+        PathBuf::from(name)
+    } else {
+        path
+    }
+}
+
+struct Scanner<'a> {
+    sandbox: &'a Path,
+    root: PathBuf,
+    include_re: Regex,
+    result: Vec<PathBuf>,
+    visited: HashSet<PathBuf>,
+    scan_complete: bool,
+}
+
+impl Scanner<'_> {
+    /// Scans a file, relative to the sandbox. `base` is the directory that its
+    /// relative paths start from: the directory of the main file, or the
+    /// directory of the `\subimport`.
+    fn scan_file(&mut self, file: &Path, base: &Path) {
+        let content = match fs::read_to_string(self.sandbox.join(file)) {
+            Ok(c) => strip_comments(&c),
+            Err(_) => {
+                warn!("while scanning, file not found: {}", file.display());
+                self.scan_complete = false;
+                return;
+            }
+        };
+
+        let captures: Vec<(String, String, Option<String>)> = self
+            .include_re
+            .captures_iter(&content)
+            .map(|cap| {
+                (
+                    cap[1].to_string(),
+                    cap[2].trim().to_string(),
+                    cap.get(3).map(|m| m.as_str().trim().to_string()),
+                )
+            })
+            .collect();
+
+        for (command, arg, second) in captures {
+            if arg.contains('#') || second.as_deref().is_some_and(|s| s.contains('#')) {
+                continue;
+            }
+            match (command.as_str(), second) {
+                ("input", _) => self.add_tex(tex_path(base, &arg, false), base),
+                ("include", _) => self.add_tex(tex_path(base, &arg, true), base),
+                ("subimport", Some(file)) => {
+                    let dir = normalize(&base.join(&arg));
+                    self.add_tex(tex_path(&dir, &file, false), &dir);
+                }
+                ("import", Some(file)) => {
+                    let dir = normalize(&self.root.join(&arg));
+                    self.add_tex(tex_path(&dir, &file, false), &dir);
+                }
+                ("includegraphics", _) => self.add_graphics(base, &arg),
+                _ => {}
+            }
+        }
+    }
+
+    fn add_tex(&mut self, path: PathBuf, base: &Path) {
+        if !self.visited.insert(path.clone()) {
+            return;
+        }
+        self.result.push(path.clone());
+        self.scan_file(&path, base);
+    }
+
+    fn add_graphics(&mut self, base: &Path, arg: &str) {
+        let path = normalize(&base.join(arg));
+        let found = if path.extension().is_some() {
+            Some(path.clone())
+        } else {
+            GRAPHICS_EXTENSIONS
+                .iter()
+                .map(|ext| path.with_extension(ext))
+                .find(|p| self.sandbox.join(p).exists())
+        };
+        match found {
+            Some(p) => {
+                if self.visited.insert(p.clone()) {
+                    self.result.push(p);
+                }
+            }
+            None => {
+                // Might be generated later, with an extension we cannot guess yet
+                warn!("cannot find graphics file: {}", path.display());
+                self.scan_complete = false;
+            }
+        }
+    }
+}
+
+impl GNode for PdfFile {
+    fn build(&self, sandbox: &Path, _predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
+        let main = self.main_tex();
+        let dir = sandbox.join(main.parent().unwrap_or(Path::new("")));
+        let Some(file_name) = main.file_name() else {
+            return false;
+        };
+        let aux = sandbox.join(PathBuf::from(&self.name).with_extension("aux"));
+
+        let mut previous_aux = fs::read(&aux).ok();
+        for run in 1..=self.max_runs {
+            let mut cmd = Command::new(&self.engine);
+            cmd.current_dir(&dir)
+                .arg("-interaction=nonstopmode")
+                .arg("-halt-on-error")
+                .arg("-file-line-error")
+                .arg(file_name);
+            if !run_command(&mut cmd, sandbox, &self.name) {
+                return false;
+            }
+            let current_aux = fs::read(&aux).ok();
+            if current_aux == previous_aux {
+                info!("{}: aux file stable after {run} runs", self.name);
+                return true;
+            }
+            previous_aux = current_aux;
+        }
+        warn!(
+            "{}: aux file still changing after {} runs",
+            self.name, self.max_runs
+        );
+        true
+    }
+
+    fn scan(
+        &self,
+        sandbox: &Path,
+        _predecessors: &[&(dyn GNode + Send + Sync)],
+    ) -> (bool, Vec<PathBuf>) {
+        info!("scan {}", self.pathbuf().display());
+        let main = self.main_tex();
+        let root = main.parent().unwrap_or(Path::new("")).to_path_buf();
+        let mut scanner = Scanner {
+            sandbox,
+            root: root.clone(),
+            include_re: Regex::new(
+                r"\\(includegraphics|include|input|subimport|import)\s*(?:\[[^\]]*\])?\s*\{([^}]*)\}(?:\s*\{([^}]*)\})?",
+            )
+            .unwrap(),
+            result: Vec::new(),
+            visited: HashSet::new(),
+            scan_complete: true,
+        };
+        scanner.scan_file(&main, &root);
+
+        if !scanner.scan_complete {
+            info!("scan incomplete for {}", self.pathbuf().display());
+        }
+        (scanner.scan_complete, scanner.result)
+    }
+
+    fn fingerprint(&self, _predecessors: &[&(dyn GNode + Send + Sync)]) -> Option<String> {
+        Some(self.engine.clone())
+    }
+
+    fn tag(&self) -> String {
+        "PdfFile".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
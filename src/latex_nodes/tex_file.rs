@@ -0,0 +1,24 @@
+use crate::model::GRootNode;
+use std::path::PathBuf;
+
+pub struct TexFile {
+    pub name: String,
+}
+
+impl TexFile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+        }
+    }
+}
+
+impl GRootNode for TexFile {
+    fn tag(&self) -> String {
+        "TexFile".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
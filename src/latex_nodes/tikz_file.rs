@@ -0,0 +1,24 @@
+use crate::model::GRootNode;
+use std::path::PathBuf;
+
+pub struct TikzFile {
+    pub name: String,
+}
+
+impl TikzFile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+        }
+    }
+}
+
+impl GRootNode for TikzFile {
+    fn tag(&self) -> String {
+        "TikzFile".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
@@ -1,7 +1,15 @@
 //! yamake - yet another make tool
 
+pub mod archive_nodes;
 pub mod c_nodes;
 pub mod command;
+mod compile_commands;
+pub mod depfile;
+pub mod generic_nodes;
+pub mod latex_nodes;
 mod make;
+pub mod markdown_nodes;
 pub mod model;
 mod mount;
+mod paths;
+mod progress;
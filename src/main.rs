@@ -1,6 +1,9 @@
-use log::info;
+use log::{info, warn};
 
 fn main() {
     env_logger::init();
+    if let Err(e) = yamake::command::install_interrupt_handler() {
+        warn!("cannot install Ctrl-C handler: {e}");
+    }
     info!("Hello from yamake!");
 }
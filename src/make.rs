@@ -1,54 +1,165 @@
-use crate::model::{EdgeType, G, GNodeStatus, MakeOutput, OutputInfo, PredecessorInfo};
-use log::error;
+use crate::command;
+use crate::model::{
+    EdgeType, ExternalDependency, G, GNode, GNodeStatus, MakeMode, MakeOutput, OutputInfo,
+    PredecessorInfo, RebuildReason, TestResult,
+};
+use crate::progress::Progress;
+use log::{error, warn};
 use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
 use rayon::prelude::*;
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fs::{self, File};
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
+use std::thread;
+use std::time::Duration;
 
+/// Digest of a file, or the Merkle hash of a directory (see `compute_dir_digest`).
 pub(crate) fn compute_file_digest(path: &Path) -> Option<String> {
-    let content = fs::read(path).ok()?;
+    if path.is_dir() {
+        return compute_dir_digest(path);
+    }
     let mut hasher = Sha256::new();
-    hasher.update(&content);
+    hasher.update(fs::read(path).ok()?);
     Some(hex::encode(hasher.finalize()))
 }
 
-fn load_previous_digests(path: &Path) -> HashMap<String, String> {
+/// Merkle hash of a directory: the digest of its entries sorted by name, each
+/// one a kind, a name and the digest of a file or subdirectory, or the target
+/// of a symbolic link. It does not depend on timestamps or on the order of
+/// the entries on disk, and an empty subdirectory changes it.
+fn compute_dir_digest(path: &Path) -> Option<String> {
+    let mut entries: Vec<fs::DirEntry> = fs::read_dir(path).ok()?.collect::<Result<_, _>>().ok()?;
+    entries.sort_by_key(|e| e.file_name());
+    let mut hasher = Sha256::new();
+    for entry in entries {
+        let file_type = entry.file_type().ok()?;
+        let (kind, digest) = if file_type.is_symlink() {
+            let target = fs::read_link(entry.path()).ok()?;
+            ("l", target.to_string_lossy().to_string())
+        } else if file_type.is_dir() {
+            ("d", compute_dir_digest(&entry.path())?)
+        } else {
+            ("f", compute_file_digest(&entry.path())?)
+        };
+        hasher.update(kind.as_bytes());
+        hasher.update([0]);
+        hasher.update(entry.file_name().as_encoded_bytes());
+        hasher.update([0]);
+        hasher.update(digest.as_bytes());
+        hasher.update([0]);
+    }
+    Some(hex::encode(hasher.finalize()))
+}
+
+/// Removes the previous content of a directory output, so that files the
+/// new build does not write are not left behind, and creates it empty.
+///
+/// The pathbuf must name a directory inside the sandbox: an empty, absolute
+/// pathbuf or one with `.` or `..` components (which would remove the sandbox
+/// or files outside of it) is rejected.
+fn clear_output_dir(sandbox: &Path, pathbuf: &Path) -> std::io::Result<()> {
+    let inside = pathbuf.components().next().is_some()
+        && pathbuf
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)));
+    if !inside {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{} is not a directory of the sandbox", pathbuf.display()),
+        ));
+    }
+    let path = sandbox.join(pathbuf);
+    let path = path.as_path();
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path)?,
+        Ok(_) => fs::remove_file(path)?,
+        Err(_) => {}
+    }
+    fs::create_dir_all(path)
+}
+
+/// Data recorded in make-report.yml by the previous run.
+#[derive(Default)]
+pub(crate) struct PreviousReport {
+    /// Digest of each node, keyed by pathbuf.
+    pub digests: HashMap<String, String>,
+    /// Predecessor pathbufs of each node, keyed by pathbuf.
+    pub predecessors: HashMap<String, Vec<PathBuf>>,
+    /// Digest of the external dependencies of each node, keyed by pathbuf.
+    pub external_digests: HashMap<String, HashMap<PathBuf, Option<String>>>,
+    /// Fingerprint digest of each node, keyed by pathbuf.
+    pub fingerprints: HashMap<String, String>,
+    /// Dependencies found by the last successful build of each node, keyed by pathbuf.
+    pub discovered: HashMap<String, Vec<PathBuf>>,
+}
+
+fn load_previous_report(path: &Path) -> PreviousReport {
     let file = match File::open(path) {
         Ok(f) => f,
-        Err(_) => return HashMap::new(),
+        Err(_) => return PreviousReport::default(),
     };
     let output: MakeOutput = match serde_yaml::from_reader(file) {
         Ok(o) => o,
-        Err(_) => return HashMap::new(),
+        Err(_) => return PreviousReport::default(),
     };
-    output
-        .nodes
-        .into_iter()
-        .filter_map(|info| {
-            info.digest
-                .map(|d| (info.pathbuf.to_string_lossy().to_string(), d))
-        })
-        .collect()
+    let mut previous = PreviousReport::default();
+    for info in output.nodes {
+        let key = info.pathbuf.to_string_lossy().to_string();
+        previous.predecessors.insert(
+            key.clone(),
+            info.predecessors.into_iter().map(|p| p.pathbuf).collect(),
+        );
+        previous.external_digests.insert(
+            key.clone(),
+            info.external_dependencies
+                .into_iter()
+                .map(|e| (e.pathbuf, e.digest))
+                .collect(),
+        );
+        if let Some(deps) = info.discovered_dependencies {
+            previous.discovered.insert(key.clone(), deps);
+        }
+        if let Some(f) = info.fingerprint {
+            previous.fingerprints.insert(key.clone(), f);
+        }
+        if let Some(d) = info.digest {
+            previous.digests.insert(key, d);
+        }
+    }
+    previous
 }
 
 impl G {
     pub fn make(&mut self) -> bool {
         // Load previous digests from make-report.yml
         let report_path = self.sandbox.join("make-report.yml");
-        let previous_digests = load_previous_digests(&report_path);
+        let previous = load_previous_report(&report_path);
 
         // Set all node statuses to Initial
         for node_idx in self.g.node_indices() {
             self.nodes_status.insert(node_idx, GNodeStatus::Initial);
         }
+        self.rebuild_reasons.clear();
+        self.build_attempts.clear();
+        self.scanned_dependencies.clear();
+        self.discovered_dependencies.clear();
+        self.external_dependencies.clear();
+        command::reset_sandbox(&self.sandbox, self.stream_output);
+
+        // Live display on a terminal, unless command output is streamed there
+        let progress = if self.stream_output {
+            None
+        } else {
+            Progress::new(self.g.node_count() as u64)
+        };
 
         loop {
             let digest_before = self.graph_digest();
 
             // Mount root nodes
-            self.mount_root_nodes(&previous_digests);
+            self.mount_root_nodes(&previous.digests);
 
             // Reset ScanIncomplete nodes to Initial so they can be re-scanned
             for node_idx in self.g.node_indices() {
@@ -61,13 +172,19 @@ impl G {
             self.expand_nodes();
 
             // Scan nodes to discover dependencies (after expand, so generated nodes exist)
-            self.scan_nodes();
+            self.scan_nodes(&previous);
 
             // Build nodes
-            self.build_nodes(&previous_digests);
+            command::set_timeouts(&self.sandbox, self.timeout, self.node_timeouts());
+            self.build_nodes(&previous, progress.as_ref());
 
-            // Print status summary for this iteration
-            self.print_status();
+            // Update the live display, or print a status summary for this iteration
+            match &progress {
+                Some(progress) => {
+                    progress.set_counts(self.done_count() as u64, self.g.node_count() as u64)
+                }
+                None => self.print_status(),
+            }
 
             // Verify all nodes have a status
             assert_eq!(
@@ -78,24 +195,78 @@ impl G {
                 self.g.node_count()
             );
 
+            // Stop at the first failure in fail-fast mode, or on Ctrl-C
+            if command::is_cancelled(&self.sandbox) {
+                self.cancel_pending_nodes();
+                break;
+            }
+
             let digest_after = self.graph_digest();
             if digest_after == digest_before {
                 break;
             }
         }
 
+        if let Some(progress) = &progress {
+            progress.clear();
+            self.print_status();
+        }
+
         // Save digests to make-report.yml
         self.save_digests();
 
+        if let Some(path) = &self.compile_commands
+            && let Err(e) = self.write_compile_commands(path)
+        {
+            error!("Failed to write {}: {e}", path.display());
+        }
+
         // Return false if any node failed
         !self.nodes_status.values().any(|&status| {
             matches!(
                 status,
-                GNodeStatus::BuildFailed | GNodeStatus::MountedFailed | GNodeStatus::AncestorFailed
+                GNodeStatus::BuildFailed
+                    | GNodeStatus::MountedFailed
+                    | GNodeStatus::AncestorFailed
+                    | GNodeStatus::Cancelled
+                    | GNodeStatus::TimedOut
             )
         })
     }
 
+    /// Number of nodes that reached a final status.
+    fn done_count(&self) -> usize {
+        self.nodes_status
+            .values()
+            .filter(|status| {
+                !matches!(
+                    status,
+                    GNodeStatus::Initial | GNodeStatus::ScanIncomplete | GNodeStatus::Running
+                )
+            })
+            .count()
+    }
+
+    /// Timeouts defined by the nodes themselves, keyed by pathbuf.
+    fn node_timeouts(&self) -> HashMap<String, Duration> {
+        self.g
+            .node_weights()
+            .filter_map(|node| {
+                node.timeout()
+                    .map(|t| (node.pathbuf().to_string_lossy().to_string(), t))
+            })
+            .collect()
+    }
+
+    /// Mark nodes that were never started as `Cancelled`.
+    fn cancel_pending_nodes(&mut self) {
+        for status in self.nodes_status.values_mut() {
+            if matches!(status, GNodeStatus::Initial | GNodeStatus::ScanIncomplete) {
+                *status = GNodeStatus::Cancelled;
+            }
+        }
+    }
+
     /// Save digests and status to make-report.yml.
     fn save_digests(&self) {
         let mut infos: Vec<OutputInfo> = Vec::new();
@@ -109,7 +280,21 @@ impl G {
                 .get(&node_idx)
                 .copied()
                 .unwrap_or(GNodeStatus::Initial);
-            let digest = compute_file_digest(&file_path);
+            let attempts = self.build_attempts.get(&node_idx).copied().unwrap_or(0);
+            // Settings and digests are only recorded for an output that was built
+            // with them, so that a node left unbuilt (or half built, like the
+            // directory of a failed command) is rebuilt next time
+            let up_to_date = matches!(
+                status,
+                GNodeStatus::BuildSuccess
+                    | GNodeStatus::BuildNotChanged
+                    | GNodeStatus::BuildNotRequired
+            );
+            let mounted = matches!(
+                status,
+                GNodeStatus::MountedChanged | GNodeStatus::MountedNotChanged
+            );
+            let digest = compute_file_digest(&file_path).filter(|_| up_to_date || mounted);
 
             // Collect predecessors with their status
             let predecessors: Vec<PredecessorInfo> = self
@@ -139,6 +324,41 @@ impl G {
                 predecessors,
                 expanded: false,
                 tag: node.tag(),
+                rebuild_reason: self.rebuild_reasons.get(&node_idx).cloned(),
+                scanned_dependencies: self
+                    .scanned_dependencies
+                    .get(&node_idx)
+                    .cloned()
+                    .unwrap_or_default(),
+                discovered_dependencies: self.discovered_dependencies.get(&node_idx).cloned(),
+                external_dependencies: self
+                    .external_dependencies
+                    .get(&node_idx)
+                    .map(|paths| {
+                        paths
+                            .iter()
+                            .map(|p| ExternalDependency {
+                                pathbuf: p.clone(),
+                                digest: compute_file_digest(p).filter(|_| up_to_date),
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                fingerprint: self.fingerprint_digest(node_idx).filter(|_| up_to_date),
+                test_result: match status {
+                    _ if !node.is_test() => None,
+                    GNodeStatus::BuildSuccess
+                    | GNodeStatus::BuildNotChanged
+                    | GNodeStatus::BuildNotRequired => Some(TestResult::Passed),
+                    GNodeStatus::BuildFailed | GNodeStatus::TimedOut => Some(TestResult::Failed),
+                    _ => None,
+                },
+                attempts,
+                flaky: attempts > 1
+                    && matches!(
+                        status,
+                        GNodeStatus::BuildSuccess | GNodeStatus::BuildNotChanged
+                    ),
             });
         }
 
@@ -164,24 +384,31 @@ impl G {
     ///
     /// For each node, call its scan method to find additional dependencies
     /// and add edges for any discovered dependencies that exist in the graph.
-    fn scan_nodes(&mut self) {
+    /// Absolute paths outside the sandbox are kept as external dependencies.
+    fn scan_nodes(&mut self, previous: &PreviousReport) {
         let node_indices: Vec<NodeIndex> = self.g.node_indices().collect();
 
         for node_idx in node_indices {
             // Get predecessors for this node
-            let pred_indices: Vec<NodeIndex> = self
-                .g
-                .neighbors_directed(node_idx, petgraph::Direction::Incoming)
-                .collect();
+            let pred_indices = self.predecessor_indices(node_idx);
 
             let predecessors: Vec<&(dyn crate::model::GNode + Send + Sync)> = pred_indices
                 .iter()
                 .map(|&idx| self.g[idx].as_ref())
                 .collect();
 
-            // Call scan on the node
-            let (scan_complete, scanned_paths) =
-                self.g[node_idx].scan(&self.sandbox, &predecessors);
+            // What the last build found (e.g. the depfile of a compiler) is
+            // exact: it replaces the scan, which only bootstraps the first build
+            if !self.discovered_dependencies.contains_key(&node_idx) {
+                let key = self.g[node_idx].pathbuf().to_string_lossy().to_string();
+                if let Some(deps) = previous.discovered.get(&key) {
+                    self.discovered_dependencies.insert(node_idx, deps.clone());
+                }
+            }
+            let (scan_complete, scanned_paths) = match self.discovered_dependencies.get(&node_idx) {
+                Some(deps) => (true, deps.clone()),
+                None => self.g[node_idx].scan(&self.sandbox, &predecessors),
+            };
 
             // If scan is not complete, mark node as ScanIncomplete
             if !scan_complete {
@@ -189,6 +416,16 @@ impl G {
                     .insert(node_idx, GNodeStatus::ScanIncomplete);
             }
 
+            // Keep the discovered dependencies for the report
+            self.scanned_dependencies
+                .insert(node_idx, scanned_paths.clone());
+            let external: Vec<PathBuf> = scanned_paths
+                .iter()
+                .filter(|p| p.is_absolute() && !p.starts_with(&self.sandbox))
+                .cloned()
+                .collect();
+            self.external_dependencies.insert(node_idx, external);
+
             // Add edges for discovered dependencies
             for path in scanned_paths {
                 // Find if there's a node with this path
@@ -227,9 +464,23 @@ impl G {
     /// Nodes with ScanIncomplete status are skipped.
     /// If all predecessors are unchanged and output exists with same digest, skip build.
     /// Builds are executed concurrently using Rayon.
-    fn build_nodes(&mut self, previous_digests: &HashMap<String, String>) {
+    fn build_nodes(&mut self, previous: &PreviousReport, progress: Option<&Progress>) {
+        let previous_digests = &previous.digests;
         let node_indices: Vec<NodeIndex> = self.g.node_indices().collect();
 
+        // Objects linked into a shared library, which expand may have added
+        for &node_idx in &node_indices {
+            let object = matches!(self.g[node_idx].tag().as_str(), "OFile" | "CxxOFile");
+            if object
+                && self
+                    .g
+                    .neighbors_directed(node_idx, petgraph::Direction::Outgoing)
+                    .any(|succ_idx| self.g[succ_idx].tag() == "SoFile")
+            {
+                self.g[node_idx].set_position_independent();
+            }
+        }
+
         // First pass: mark AncestorFailed and BuildNotRequired nodes (no actual building)
         let mut nodes_to_expand: Vec<NodeIndex> = Vec::new();
         let mut nodes_to_build: Vec<NodeIndex> = Vec::new();
@@ -263,6 +514,7 @@ impl G {
                         Some(GNodeStatus::BuildFailed)
                             | Some(GNodeStatus::AncestorFailed)
                             | Some(GNodeStatus::MountedFailed)
+                            | Some(GNodeStatus::TimedOut)
                     )
                 });
 
@@ -320,8 +572,13 @@ impl G {
                     )
                 });
 
-            // If all predecessors unchanged, check if output exists with same digest
-            if all_predecessors_unchanged {
+            // If all predecessors and external dependencies are unchanged, check if
+            // output exists with same digest
+            if all_predecessors_unchanged
+                && self.changed_external(node_idx, previous).is_none()
+                && !self.fingerprint_changed(node_idx, previous)
+                && self.changed_output(node_idx, previous).is_none()
+            {
                 let pathbuf = self.g[node_idx].pathbuf();
                 let pathbuf_str = pathbuf.to_string_lossy().to_string();
                 let output_path = self.sandbox.join(&pathbuf);
@@ -330,18 +587,19 @@ impl G {
                     let current_digest = compute_file_digest(&output_path);
                     if let (Some(current), Some(previous)) =
                         (&current_digest, previous_digests.get(&pathbuf_str))
+                        && current == previous
                     {
-                        if current == previous {
-                            self.nodes_status
-                                .insert(node_idx, GNodeStatus::BuildNotRequired);
-                            nodes_to_expand.push(node_idx);
-                            continue;
-                        }
+                        self.nodes_status
+                            .insert(node_idx, GNodeStatus::BuildNotRequired);
+                        nodes_to_expand.push(node_idx);
+                        continue;
                     }
                 }
             }
 
             // Node is ready to build
+            let reason = self.rebuild_reason(node_idx, previous);
+            self.rebuild_reasons.insert(node_idx, reason);
             nodes_to_build.push(node_idx);
         }
 
@@ -351,22 +609,27 @@ impl G {
         }
 
         // Build nodes concurrently
-        let build_results: Vec<(NodeIndex, GNodeStatus)> = nodes_to_build
+        let build_results: Vec<(NodeIndex, GNodeStatus, u32)> = nodes_to_build
             .par_iter()
             .map(|&node_idx| {
                 // Get predecessors for the build call
-                let pred_indices: Vec<NodeIndex> = self
-                    .g
-                    .neighbors_directed(node_idx, petgraph::Direction::Incoming)
-                    .collect();
+                let pred_indices = self.predecessor_indices(node_idx);
 
                 let predecessors: Vec<&(dyn crate::model::GNode + Send + Sync)> = pred_indices
                     .iter()
                     .map(|&idx| self.g[idx].as_ref())
                     .collect();
 
-                // Build the node
-                let build_ok = self.g[node_idx].build(&self.sandbox, &predecessors);
+                // Do not start new builds once fail-fast cancelled the make
+                if command::is_cancelled(&self.sandbox) {
+                    return (node_idx, GNodeStatus::Cancelled, 0);
+                }
+
+                // Build the node, retrying according to its retry policy
+                if let Some(progress) = progress {
+                    progress.start(node_idx, &self.g[node_idx].pathbuf());
+                }
+                let (build_ok, attempts) = self.build_with_retry(node_idx, &predecessors);
 
                 // Determine status based on build result
                 let status = if build_ok {
@@ -380,6 +643,23 @@ impl G {
                             output_path.display()
                         );
                         GNodeStatus::BuildFailed
+                    } else if let Some(missing) = self.g[node_idx]
+                        .outputs()
+                        .iter()
+                        .map(|o| self.sandbox.join(o.pathbuf()))
+                        .find(|p| !p.exists())
+                    {
+                        error!(
+                            "build succeeded but output file missing: {}",
+                            missing.display()
+                        );
+                        GNodeStatus::BuildFailed
+                    } else if self.g[node_idx].output_is_directory() && !output_path.is_dir() {
+                        error!(
+                            "build succeeded but output is not a directory: {}",
+                            output_path.display()
+                        );
+                        GNodeStatus::BuildFailed
                     } else {
                         let current_digest = compute_file_digest(&output_path);
 
@@ -390,27 +670,259 @@ impl G {
                             _ => GNodeStatus::BuildSuccess,
                         }
                     }
+                } else if command::is_timed_out(
+                    &self.sandbox,
+                    &self.g[node_idx].pathbuf().to_string_lossy(),
+                ) {
+                    GNodeStatus::TimedOut
+                } else if command::is_cancelled(&self.sandbox) {
+                    // Killed because another node failed, or on Ctrl-C
+                    GNodeStatus::Cancelled
                 } else {
                     GNodeStatus::BuildFailed
                 };
 
-                (node_idx, status)
+                if matches!(status, GNodeStatus::BuildFailed | GNodeStatus::TimedOut)
+                    && self.mode == MakeMode::FailFast
+                {
+                    error!(
+                        "build failed, cancelling make: {}",
+                        self.g[node_idx].pathbuf().display()
+                    );
+                    command::cancel_commands(&self.sandbox);
+                }
+
+                if let Some(progress) = progress {
+                    progress.finish(node_idx, &self.g[node_idx].pathbuf(), status);
+                }
+
+                (node_idx, status, attempts)
             })
             .collect();
 
         // Update statuses from build results
-        for (node_idx, status) in build_results {
+        for (node_idx, status, attempts) in build_results {
             self.nodes_status.insert(node_idx, status);
+            self.build_attempts.insert(node_idx, attempts);
+            if matches!(
+                status,
+                GNodeStatus::BuildSuccess | GNodeStatus::BuildNotChanged
+            ) && let Some(deps) = self.g[node_idx].discovered_dependencies(&self.sandbox)
+            {
+                self.discovered_dependencies.insert(node_idx, deps);
+            }
         }
     }
 
-    /// Expand a single node, adding any new nodes and edges to the graph.
-    fn expand_single_node(&mut self, node_idx: NodeIndex) {
-        // Get predecessor indices
-        let pred_indices: Vec<NodeIndex> = self
+    /// Build a node, retrying on failure as long as its retry policy allows.
+    ///
+    /// When the node has a retry policy, the logs of each attempt are kept as
+    /// `<node>.stdout.<n>` and `<node>.stderr.<n>`.
+    /// Returns the build result and the number of attempts.
+    fn build_with_retry(
+        &self,
+        node_idx: NodeIndex,
+        predecessors: &[&(dyn GNode + Send + Sync)],
+    ) -> (bool, u32) {
+        let node = &self.g[node_idx];
+        let node_id = node.pathbuf().to_string_lossy().to_string();
+        let policy = node.retry_policy();
+        let mut attempt = 1;
+        loop {
+            command::begin_attempt(&self.sandbox, &node_id);
+            if node.output_is_directory()
+                && let Err(e) = clear_output_dir(&self.sandbox, &node.pathbuf())
+            {
+                error!("cannot clear output directory {node_id}: {e}");
+                return (false, attempt);
+            }
+            let build_ok = node.build(&self.sandbox, predecessors);
+            let Some(policy) = &policy else {
+                return (build_ok, attempt);
+            };
+            command::keep_attempt_logs(&self.sandbox, &node_id, attempt);
+            if build_ok
+                || attempt >= policy.max_attempts
+                || command::is_cancelled(&self.sandbox)
+                || !policy.retries(command::last_exit_code(&self.sandbox, &node_id))
+            {
+                return (build_ok, attempt);
+            }
+            let delay = policy.delay(attempt);
+            warn!(
+                "build of {node_id} failed (attempt {attempt}/{}), retrying in {delay:?}",
+                policy.max_attempts
+            );
+            thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    /// Determine why a node that is about to be built needs rebuilding.
+    ///
+    /// Checked in order: missing output, predecessor absent from the previous
+    /// report, first changed predecessor (by pathbuf), first changed external
+    /// dependency, changed fingerprint, first changed extra output, output
+    /// digest mismatch.
+    fn rebuild_reason(&self, node_idx: NodeIndex, previous: &PreviousReport) -> RebuildReason {
+        let pathbuf = self.g[node_idx].pathbuf();
+        let pathbuf_str = pathbuf.to_string_lossy().to_string();
+        let output_path = self.sandbox.join(&pathbuf);
+
+        let outputs_missing = self.g[node_idx]
+            .outputs()
+            .iter()
+            .any(|o| !self.sandbox.join(o.pathbuf()).exists());
+        if !output_path.exists() || outputs_missing {
+            return RebuildReason::OutputMissing;
+        }
+
+        let mut preds: Vec<(PathBuf, NodeIndex, EdgeType)> = self
+            .g
+            .edges_directed(node_idx, petgraph::Direction::Incoming)
+            .map(|e| (self.g[e.source()].pathbuf(), e.source(), *e.weight()))
+            .collect();
+        preds.sort_by(|a, b| a.0.cmp(&b.0));
+
+        if let Some(previous_preds) = previous.predecessors.get(&pathbuf_str)
+            && let Some((pred_path, _, edge_type)) =
+                preds.iter().find(|(p, _, _)| !previous_preds.contains(p))
+        {
+            return RebuildReason::NewDependency {
+                pathbuf: pred_path.clone(),
+                edge_type: *edge_type,
+            };
+        }
+
+        let changed = preds.iter().find(|(_, idx, _)| {
+            !matches!(
+                self.nodes_status.get(idx),
+                Some(GNodeStatus::MountedNotChanged)
+                    | Some(GNodeStatus::BuildNotChanged)
+                    | Some(GNodeStatus::BuildNotRequired)
+            )
+        });
+        if let Some((pred_path, _, _)) = changed {
+            return RebuildReason::PredecessorChanged {
+                pathbuf: pred_path.clone(),
+                previous_digest: previous
+                    .digests
+                    .get(&pred_path.to_string_lossy().to_string())
+                    .cloned(),
+                current_digest: compute_file_digest(&self.sandbox.join(pred_path)),
+            };
+        }
+
+        if let Some((pathbuf, previous_digest, current_digest)) =
+            self.changed_external(node_idx, previous)
+        {
+            return RebuildReason::ExternalChanged {
+                pathbuf,
+                previous_digest,
+                current_digest,
+            };
+        }
+
+        if self.fingerprint_changed(node_idx, previous) {
+            return RebuildReason::FingerprintChanged {
+                previous_digest: previous.fingerprints.get(&pathbuf_str).cloned(),
+                current_digest: self.fingerprint_digest(node_idx),
+            };
+        }
+
+        if let Some((pathbuf, previous_digest, current_digest)) =
+            self.changed_output(node_idx, previous)
+        {
+            return RebuildReason::OutputChanged {
+                pathbuf,
+                previous_digest,
+                current_digest,
+            };
+        }
+
+        RebuildReason::DigestMismatch {
+            previous_digest: previous.digests.get(&pathbuf_str).cloned(),
+            current_digest: compute_file_digest(&output_path),
+        }
+    }
+
+    /// Find the first external dependency of a node whose digest differs from the
+    /// one in the previous report, or that the previous report did not list.
+    /// Returns its path with the previous and current digests.
+    fn changed_external(
+        &self,
+        node_idx: NodeIndex,
+        previous: &PreviousReport,
+    ) -> Option<(PathBuf, Option<String>, Option<String>)> {
+        let external = self.external_dependencies.get(&node_idx)?;
+        let pathbuf_str = self.g[node_idx].pathbuf().to_string_lossy().to_string();
+        let previous_digests = previous.external_digests.get(&pathbuf_str);
+        external.iter().find_map(|path| {
+            let current = compute_file_digest(path);
+            match previous_digests.and_then(|d| d.get(path)) {
+                Some(previous) if *previous == current => None,
+                Some(previous) => Some((path.clone(), previous.clone(), current)),
+                None => Some((path.clone(), None, current)),
+            }
+        })
+    }
+
+    /// Find the first extra output of a node that is missing, or whose digest
+    /// differs from the one in the previous report.
+    fn changed_output(
+        &self,
+        node_idx: NodeIndex,
+        previous: &PreviousReport,
+    ) -> Option<(PathBuf, Option<String>, Option<String>)> {
+        self.g[node_idx].outputs().into_iter().find_map(|output| {
+            let path = output.pathbuf();
+            let current = compute_file_digest(&self.sandbox.join(&path));
+            let recorded = previous
+                .digests
+                .get(&path.to_string_lossy().to_string())
+                .cloned();
+            match (&current, &recorded) {
+                (Some(c), Some(r)) if c == r => None,
+                _ => Some((path, recorded, current)),
+            }
+        })
+    }
+
+    /// The predecessors of a node, in the order their edges were added: the
+    /// order in which the nodes see them.
+    pub(crate) fn predecessor_indices(&self, node_idx: NodeIndex) -> Vec<NodeIndex> {
+        // petgraph lists the most recently added edge first
+        let mut indices: Vec<NodeIndex> = self
             .g
             .neighbors_directed(node_idx, petgraph::Direction::Incoming)
             .collect();
+        indices.reverse();
+        indices
+    }
+
+    /// Digest of the fingerprint of a node, given its predecessors.
+    fn fingerprint_digest(&self, node_idx: NodeIndex) -> Option<String> {
+        let predecessors: Vec<&(dyn crate::model::GNode + Send + Sync)> = self
+            .predecessor_indices(node_idx)
+            .into_iter()
+            .map(|idx| self.g[idx].as_ref())
+            .collect();
+        let fingerprint = self.g[node_idx].fingerprint(&predecessors)?;
+        let mut hasher = Sha256::new();
+        hasher.update(fingerprint.as_bytes());
+        Some(hex::encode(hasher.finalize()))
+    }
+
+    /// Whether the fingerprint digest of a node differs from the previous report.
+    fn fingerprint_changed(&self, node_idx: NodeIndex, previous: &PreviousReport) -> bool {
+        let pathbuf_str = self.g[node_idx].pathbuf().to_string_lossy().to_string();
+        self.fingerprint_digest(node_idx).as_ref() != previous.fingerprints.get(&pathbuf_str)
+    }
+
+    /// Expand a single node, adding any new nodes and edges to the graph.
+    fn expand_single_node(&mut self, node_idx: NodeIndex) {
+        // Get predecessor indices
+        let pred_indices = self.predecessor_indices(node_idx);
 
         // Clone sandbox path to avoid borrow issues
         let sandbox = self.sandbox.clone();
@@ -436,6 +948,7 @@ impl G {
                 if !exists {
                     let new_idx = self.g.add_node(node);
                     self.nodes_status.insert(new_idx, GNodeStatus::Initial);
+                    self.add_outputs(new_idx);
                 }
             }
 
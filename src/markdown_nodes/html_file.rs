@@ -0,0 +1,214 @@
+use crate::command::log_build;
+use crate::generic_nodes::template_node::{predecessor, render};
+use crate::model::GNode;
+use crate::paths::normalize;
+use log::{error, info, warn};
+use markdown_heading_id::HeadingId;
+use minijinja::context;
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd, html};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// An HTML page, rendered from its `MarkdownFile` predecessor with
+/// pulldown-cmark (tables, footnotes, strikethrough and task lists).
+///
+/// Headings are rendered with markdown-heading-id: `## Heading {#id}` gets
+/// the id `id`. A `mermaid` code block becomes a `<pre class="mermaid">` for
+/// mermaid.js, e.g. the output of `G::to_mermaid`.
+///
+/// With a `TemplateFile` predecessor, the page is the template rendered with
+/// `content` (the HTML of the markdown), `title` (the text of the first
+/// heading) and `name`. The scan finds the local links and images, relative
+/// to the markdown file; a missing image makes the scan incomplete, as it
+/// may be generated. Links to other pages (`.html`, `.htm`) are not
+/// dependencies: pages linking each other would make a cycle.
+pub struct HtmlFile {
+    pub name: String,
+}
+
+impl HtmlFile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+        }
+    }
+
+    fn render(
+        &self,
+        sandbox: &Path,
+        predecessors: &[&(dyn GNode + Send + Sync)],
+    ) -> Result<String, String> {
+        let markdown = predecessor(sandbox, predecessors, "MarkdownFile")?;
+        let source = fs::read_to_string(&markdown)
+            .map_err(|e| format!("cannot read {}: {e}", markdown.display()))?;
+        let (content, title) = to_html(&source);
+
+        let templates: Vec<_> = predecessors
+            .iter()
+            .filter(|p| p.tag() == "TemplateFile")
+            .collect();
+        match templates.as_slice() {
+            [] => Ok(content),
+            [template] => {
+                let path = sandbox.join(template.pathbuf());
+                let template = fs::read_to_string(&path)
+                    .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+                render(
+                    &template,
+                    context! { content, title, name => self.name.clone() },
+                )
+            }
+            found => Err(format!(
+                "expected at most one TemplateFile predecessor, found {}",
+                found.len()
+            )),
+        }
+    }
+}
+
+fn options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+}
+
+/// Renders a heading with markdown-heading-id, which turns
+/// `## Usage {#usage}` into `<h2 id="usage">Usage</h2>`.
+fn heading_html(source: &str) -> String {
+    let parser = HeadingId::new(pulldown_cmark_headings::Parser::new(source));
+    let mut html = String::new();
+    pulldown_cmark_headings::html::push_html(&mut html, parser);
+    html
+}
+
+/// Renders markdown, and returns the HTML and the text of the first heading.
+fn to_html(source: &str) -> (String, Option<String>) {
+    let mut title = None;
+    let mut events: Vec<Event> = Vec::new();
+    let mut parser = Parser::new_ext(source, options()).into_offset_iter();
+
+    while let Some((event, range)) = parser.next() {
+        match event {
+            Event::Start(Tag::Heading { .. }) => {
+                let mut text = String::new();
+                for (event, _) in parser.by_ref() {
+                    match event {
+                        Event::End(TagEnd::Heading(_)) => break,
+                        Event::Text(t) | Event::Code(t) => text.push_str(&t),
+                        _ => {}
+                    }
+                }
+                // Without the id
+                title.get_or_insert_with(|| match text.split_once("{#") {
+                    Some((text, _)) => text.trim_end().to_string(),
+                    None => text,
+                });
+                events.push(Event::Html(heading_html(&source[range]).into()));
+            }
+            // Mermaid diagrams are drawn by mermaid.js from the text of the block
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang))) if &*lang == "mermaid" => {
+                events.push(Event::Html("<pre class=\"mermaid\">".into()));
+                for (event, _) in parser.by_ref() {
+                    match event {
+                        Event::End(TagEnd::CodeBlock) => break,
+                        event => events.push(event),
+                    }
+                }
+                events.push(Event::Html("</pre>\n".into()));
+            }
+            event => events.push(event),
+        }
+    }
+
+    let mut content = String::new();
+    html::push_html(&mut content, events.into_iter());
+    (content, title)
+}
+
+/// A link or image destination in the project: not a URL, not absolute, not
+/// an anchor of the page. The anchor and query are removed.
+fn local_target(dest: &str) -> Option<&str> {
+    if dest.contains("://") || dest.starts_with('/') || dest.starts_with("mailto:") {
+        return None;
+    }
+    let path = dest.split(['#', '?']).next().unwrap_or("");
+    (!path.is_empty()).then_some(path)
+}
+
+impl GNode for HtmlFile {
+    fn build(&self, sandbox: &Path, predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
+        let description = format!("Render markdown: {}", self.name);
+        let result = self.render(sandbox, predecessors).and_then(|page| {
+            let path = sandbox.join(&self.name);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("cannot create {}: {e}", parent.display()))?;
+            }
+            fs::write(path, page).map_err(|e| format!("cannot write {}: {e}", self.name))
+        });
+        match result {
+            Ok(()) => log_build(sandbox, &self.name, &description, "", ""),
+            Err(e) => {
+                error!("{}: {e}", self.name);
+                log_build(sandbox, &self.name, &description, "", &format!("{e}\n"));
+                false
+            }
+        }
+    }
+
+    fn scan(
+        &self,
+        sandbox: &Path,
+        predecessors: &[&(dyn GNode + Send + Sync)],
+    ) -> (bool, Vec<PathBuf>) {
+        info!("scan {}", self.pathbuf().display());
+        let Some(markdown) = predecessors
+            .iter()
+            .find(|p| p.tag() == "MarkdownFile")
+            .map(|p| p.pathbuf())
+        else {
+            // The build reports the missing predecessor
+            return (true, Vec::new());
+        };
+        let Ok(source) = fs::read_to_string(sandbox.join(&markdown)) else {
+            warn!("while scanning, file not found: {}", markdown.display());
+            return (false, Vec::new());
+        };
+        let base = markdown.parent().unwrap_or(Path::new(""));
+
+        let mut scan_complete = true;
+        let mut result: Vec<PathBuf> = Vec::new();
+        for event in Parser::new_ext(&source, options()) {
+            let (dest, image) = match &event {
+                Event::Start(Tag::Link { dest_url, .. }) => (dest_url, false),
+                Event::Start(Tag::Image { dest_url, .. }) => (dest_url, true),
+                _ => continue,
+            };
+            let Some(target) = local_target(dest) else {
+                continue;
+            };
+            let path = normalize(&base.join(target));
+            if !image && path.extension().is_some_and(|e| e == "html" || e == "htm") {
+                continue;
+            }
+            if image && !sandbox.join(&path).exists() {
+                // Might be generated later
+                warn!("cannot find image: {}", path.display());
+                scan_complete = false;
+            }
+            if !result.contains(&path) {
+                result.push(path);
+            }
+        }
+        (scan_complete, result)
+    }
+
+    fn tag(&self) -> String {
+        "HtmlFile".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
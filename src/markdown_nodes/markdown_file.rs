@@ -0,0 +1,25 @@
+use crate::model::GRootNode;
+use std::path::PathBuf;
+
+/// A markdown source, rendered by an `HtmlFile`.
+pub struct MarkdownFile {
+    pub name: String,
+}
+
+impl MarkdownFile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+        }
+    }
+}
+
+impl GRootNode for MarkdownFile {
+    fn tag(&self) -> String {
+        "MarkdownFile".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
@@ -0,0 +1,5 @@
+pub mod html_file;
+pub mod markdown_file;
+
+pub use html_file::HtmlFile;
+pub use markdown_file::MarkdownFile;
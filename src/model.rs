@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 // ANCHOR: buildtype
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -21,14 +22,29 @@ pub enum GNodeStatus {
     BuildNotRequired,
     BuildFailed,
     AncestorFailed,
+    Cancelled,
+    TimedOut,
 }
 // ANCHOR_END: buildtype
 
+/// What `make()` does once a node ends up `BuildFailed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MakeMode {
+    /// Keep building every node that does not depend on a failed node.
+    #[default]
+    KeepGoing,
+    /// Stop at the first failure: running commands are killed, no new node is
+    /// started, and nodes left unbuilt are marked `Cancelled`.
+    FailFast,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum EdgeType {
     Explicit,
     Scanned,
     Expanded,
+    /// From a node to one of its extra outputs (`GNode::outputs`).
+    Output,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,6 +53,146 @@ pub struct PredecessorInfo {
     pub status: GNodeStatus,
 }
 
+/// How a node is rebuilt after its build failed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Maximum number of build attempts, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the second attempt, doubled for each following attempt.
+    pub backoff: Duration,
+    /// Exit codes of the last command that trigger a retry. Empty means any failure.
+    pub exit_codes: Vec<i32>,
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32, backoff: Duration, exit_codes: Vec<i32>) -> Self {
+        Self {
+            max_attempts,
+            backoff,
+            exit_codes,
+        }
+    }
+
+    /// Returns true if a build that failed with this exit code should be retried.
+    ///
+    /// `None` means the build failed without a command exit code (e.g. the command
+    /// was killed or the build is pure Rust); it is only retried if `exit_codes` is empty.
+    pub fn retries(&self, exit_code: Option<i32>) -> bool {
+        self.exit_codes.is_empty() || exit_code.is_some_and(|c| self.exit_codes.contains(&c))
+    }
+
+    /// Delay to wait after the given failed attempt (starting at 1).
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.backoff * 2u32.saturating_pow(attempt.saturating_sub(1))
+    }
+}
+
+/// Why a node was rebuilt during the last call to [`G::make`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RebuildReason {
+    /// The output file was missing in the sandbox.
+    OutputMissing,
+    /// A predecessor was not listed in the previous report (new scanned or expanded edge).
+    NewDependency {
+        pathbuf: PathBuf,
+        edge_type: EdgeType,
+    },
+    /// A predecessor changed since the previous build.
+    PredecessorChanged {
+        pathbuf: PathBuf,
+        previous_digest: Option<String>,
+        current_digest: Option<String>,
+    },
+    /// A dependency outside the sandbox changed, or was not recorded in the previous report.
+    ExternalChanged {
+        pathbuf: PathBuf,
+        previous_digest: Option<String>,
+        current_digest: Option<String>,
+    },
+    /// The fingerprint of the node (its settings) changed since the previous build.
+    FingerprintChanged {
+        previous_digest: Option<String>,
+        current_digest: Option<String>,
+    },
+    /// An extra output (`GNode::outputs`) does not match the digest recorded in the previous report.
+    OutputChanged {
+        pathbuf: PathBuf,
+        previous_digest: Option<String>,
+        current_digest: Option<String>,
+    },
+    /// The output digest does not match the one recorded in the previous report.
+    DigestMismatch {
+        previous_digest: Option<String>,
+        current_digest: Option<String>,
+    },
+}
+
+impl fmt::Display for RebuildReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let short = |d: &Option<String>| match d {
+            Some(d) => d.chars().take(12).collect::<String>(),
+            None => "none".to_string(),
+        };
+        match self {
+            RebuildReason::OutputMissing => write!(f, "output missing"),
+            RebuildReason::NewDependency { pathbuf, edge_type } => {
+                write!(f, "new {edge_type:?} dependency {}", pathbuf.display())
+            }
+            RebuildReason::PredecessorChanged {
+                pathbuf,
+                previous_digest,
+                current_digest,
+            } => write!(
+                f,
+                "predecessor {} changed ({} -> {})",
+                pathbuf.display(),
+                short(previous_digest),
+                short(current_digest)
+            ),
+            RebuildReason::ExternalChanged {
+                pathbuf,
+                previous_digest,
+                current_digest,
+            } => write!(
+                f,
+                "external dependency {} changed ({} -> {})",
+                pathbuf.display(),
+                short(previous_digest),
+                short(current_digest)
+            ),
+            RebuildReason::FingerprintChanged {
+                previous_digest,
+                current_digest,
+            } => write!(
+                f,
+                "settings changed ({} -> {})",
+                short(previous_digest),
+                short(current_digest)
+            ),
+            RebuildReason::OutputChanged {
+                pathbuf,
+                previous_digest,
+                current_digest,
+            } => write!(
+                f,
+                "output {} changed ({} -> {})",
+                pathbuf.display(),
+                short(previous_digest),
+                short(current_digest)
+            ),
+            RebuildReason::DigestMismatch {
+                previous_digest,
+                current_digest,
+            } => write!(
+                f,
+                "output digest mismatch ({} -> {})",
+                short(previous_digest),
+                short(current_digest)
+            ),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OutputInfo {
     pub pathbuf: PathBuf,
@@ -49,6 +205,67 @@ pub struct OutputInfo {
     #[serde(default)]
     pub expanded: bool,
     pub tag: String,
+    #[serde(default)]
+    pub rebuild_reason: Option<RebuildReason>,
+    /// Dependencies returned by the last scan of the node.
+    #[serde(default)]
+    pub scanned_dependencies: Vec<PathBuf>,
+    /// Dependencies found by the last successful build, see
+    /// [`GNode::discovered_dependencies`].
+    #[serde(default)]
+    pub discovered_dependencies: Option<Vec<PathBuf>>,
+    /// Scanned dependencies outside the sandbox, with their digest.
+    #[serde(default)]
+    pub external_dependencies: Vec<ExternalDependency>,
+    /// Digest of the fingerprint of the node.
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+    /// Outcome of a test node (see `GNode::is_test`), null if it did not run.
+    #[serde(default)]
+    pub test_result: Option<TestResult>,
+    /// Number of build attempts during the last run (0 if not built).
+    #[serde(default)]
+    pub attempts: u32,
+    /// The node was built successfully, but only after a retry.
+    #[serde(default)]
+    pub flaky: bool,
+}
+
+/// Outcome of a test node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TestResult {
+    Passed,
+    Failed,
+}
+
+/// A dependency outside the sandbox (e.g. a system or vendored header), which is
+/// not a node of the graph but is tracked by digest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalDependency {
+    pub pathbuf: PathBuf,
+    pub digest: Option<String>,
+}
+
+/// An entry of a compilation database (`compile_commands.json`), as read by
+/// clangd, clang-tidy and editors.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompileCommand {
+    pub directory: PathBuf,
+    pub file: PathBuf,
+    pub arguments: Vec<String>,
+    pub output: PathBuf,
+}
+
+/// Where the paths of the compilation database point to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CompileCommandsPaths {
+    /// Paths in the sandbox, as run by `make()`.
+    #[default]
+    Sandbox,
+    /// Paths of the files mounted from the source directory mapped back to it,
+    /// so that an editor opening the sources finds their entry. The include
+    /// paths of the sandbox are kept, for the generated headers.
+    Srcdir,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -135,6 +352,63 @@ pub trait GNode: Send + Sync {
     ) -> ExpandResult {
         Ok((Vec::new(), Vec::new()))
     }
+    /// Dependencies found by the last successful build, e.g. read from the
+    /// depfile a compiler wrote: relative to the sandbox, or absolute outside
+    /// of it. `make()` stores them in `make-report.yml` and uses them instead
+    /// of `scan` on the next runs: the scan only bootstraps the first build.
+    fn discovered_dependencies(&self, _sandbox: &Path) -> Option<Vec<PathBuf>> {
+        None
+    }
+    /// Maximum duration of each command this node runs with `run_command`,
+    /// using its pathbuf as node id. Overrides `G::timeout`.
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+    /// Retry policy applied by `make()` when the build of this node fails.
+    fn retry_policy(&self) -> Option<RetryPolicy> {
+        None
+    }
+    /// The settings of the node that affect its output, other than its
+    /// predecessors (command, flags...). They may depend on the tags of the
+    /// predecessors, e.g. the linker driver chosen for C++ objects. `make()`
+    /// records its digest in the report and rebuilds the node when it changes.
+    fn fingerprint(&self, _predecessors: &[&(dyn GNode + Send + Sync)]) -> Option<String> {
+        None
+    }
+    /// Called by `make()` before building an object node (tag `OFile` or
+    /// `CxxOFile`) that is a predecessor of an `SoFile`, so that it is
+    /// compiled as position independent code. The setting must be part of
+    /// the fingerprint.
+    fn set_position_independent(&mut self) {}
+    /// The output of the node is a directory (generated documentation, code
+    /// generator output...). `make()` removes it and creates it empty before
+    /// each build, so that no stale file is left, checks that the build left a
+    /// directory, and digests it with a Merkle hash of its content.
+    fn output_is_directory(&self) -> bool {
+        false
+    }
+    /// Files written by the build besides `pathbuf()` (a header generated with
+    /// its source, a `.gcno` written with an object...). Each one is added to
+    /// the graph as an [`OutputFile`] node after this one, so that other nodes
+    /// can depend on it (see [`G::node_index`]); it must exist after the build,
+    /// and is digested on its own in `make-report.yml`.
+    fn outputs(&self) -> Vec<OutputFile> {
+        Vec::new()
+    }
+    /// The node is a test: its outcome is reported as `test_result` in
+    /// `make-report.yml`, passed if it was built, failed if its build failed.
+    fn is_test(&self) -> bool {
+        false
+    }
+    /// Entries of the compilation database (`compile_commands.json`) for this
+    /// node, see [`G::write_compile_commands`].
+    fn compile_commands(
+        &self,
+        _sandbox: &Path,
+        _predecessors: &[&(dyn GNode + Send + Sync)],
+    ) -> Vec<CompileCommand> {
+        Vec::new()
+    }
     fn tag(&self) -> String;
     fn pathbuf(&self) -> PathBuf;
 }
@@ -288,11 +562,61 @@ impl<T: GRootNode + Send + Sync> GNode for T {
     }
 }
 
+/// An extra output of a node (see `GNode::outputs`), with the tag its
+/// successors filter on. It is added to the graph after the node that writes
+/// it, and its build only checks that the file is there.
+pub struct OutputFile {
+    pub name: String,
+    pub tag: String,
+}
+
+impl OutputFile {
+    pub fn new(name: &str, tag: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            tag: tag.to_string(),
+        }
+    }
+}
+
+impl GNode for OutputFile {
+    fn build(&self, _sandbox: &Path, _predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
+        // Written by its predecessor: make() checks that it exists
+        true
+    }
+
+    fn tag(&self) -> String {
+        self.tag.clone()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
+
 pub struct G {
     pub srcdir: PathBuf,
     pub sandbox: PathBuf,
     pub g: Graph<Box<dyn GNode + Send + Sync>, EdgeType>,
     pub nodes_status: HashMap<NodeIndex, GNodeStatus>,
+    pub rebuild_reasons: HashMap<NodeIndex, RebuildReason>,
+    pub build_attempts: HashMap<NodeIndex, u32>,
+    pub scanned_dependencies: HashMap<NodeIndex, Vec<PathBuf>>,
+    /// Dependencies found by the last successful build of each node.
+    pub discovered_dependencies: HashMap<NodeIndex, Vec<PathBuf>>,
+    /// Absolute paths outside the sandbox returned by `scan`.
+    pub external_dependencies: HashMap<NodeIndex, Vec<PathBuf>>,
+    pub mode: MakeMode,
+    /// Maximum duration of each command run with `run_command`, for nodes
+    /// that do not define their own `timeout`.
+    pub timeout: Option<Duration>,
+    /// Copy the output of commands run with `run_command` to the terminal while
+    /// they run, each line prefixed with `[node]`.
+    pub stream_output: bool,
+    /// Write the compilation database to this path at the end of `make()`.
+    pub compile_commands: Option<PathBuf>,
+    /// Paths used in the compilation database.
+    pub compile_commands_paths: CompileCommandsPaths,
 }
 
 impl G {
@@ -302,6 +626,16 @@ impl G {
             sandbox,
             g: Graph::new(),
             nodes_status: HashMap::new(),
+            rebuild_reasons: HashMap::new(),
+            build_attempts: HashMap::new(),
+            scanned_dependencies: HashMap::new(),
+            discovered_dependencies: HashMap::new(),
+            external_dependencies: HashMap::new(),
+            mode: MakeMode::default(),
+            timeout: None,
+            stream_output: false,
+            compile_commands: None,
+            compile_commands_paths: CompileCommandsPaths::default(),
         }
     }
 
@@ -319,11 +653,34 @@ impl G {
         node: N,
     ) -> Result<NodeIndex, GraphError> {
         self.check_duplicate(&node.pathbuf())?;
+        for output in node.outputs() {
+            self.check_duplicate(&output.pathbuf())?;
+        }
         let idx = self.g.add_node(Box::new(node));
         self.nodes_status.insert(idx, GNodeStatus::Initial);
+        self.add_outputs(idx);
         Ok(idx)
     }
 
+    /// Adds the extra outputs of a node, with an `Output` edge from the node.
+    pub(crate) fn add_outputs(&mut self, idx: NodeIndex) {
+        for output in self.g[idx].outputs() {
+            if self.node_index(&output.pathbuf()).is_some() {
+                continue;
+            }
+            let output_idx = self.g.add_node(Box::new(output));
+            self.nodes_status.insert(output_idx, GNodeStatus::Initial);
+            self.g.add_edge(idx, output_idx, EdgeType::Output);
+        }
+    }
+
+    /// Returns the node whose pathbuf is `path`, including the extra outputs of nodes.
+    pub fn node_index(&self, path: &Path) -> Option<NodeIndex> {
+        self.g
+            .node_indices()
+            .find(|&idx| self.g[idx].pathbuf() == path)
+    }
+
     pub fn add_root_node<N: GRootNode + Send + Sync + 'static>(
         &mut self,
         node: N,
@@ -338,6 +695,14 @@ impl G {
         self.g.add_edge(from, to, EdgeType::Explicit)
     }
 
+    /// Returns why the node at `path` was rebuilt during the last call to `make()`.
+    ///
+    /// Returns `None` if there is no such node or if it was not rebuilt.
+    pub fn explain(&self, path: &Path) -> Option<&RebuildReason> {
+        let idx = self.node_index(path)?;
+        self.rebuild_reasons.get(&idx)
+    }
+
     /// Returns all root nodes (nodes with no predecessors) in the predecessor tree of the given node.
     pub fn root_predecessors(&self, node_idx: NodeIndex) -> Vec<NodeIndex> {
         let mut visited: HashSet<NodeIndex> = HashSet::new();
@@ -424,6 +789,8 @@ impl G {
                 Some(GNodeStatus::BuildNotRequired) => "fill:#E6E6FA,stroke:#9370DB",
                 Some(GNodeStatus::BuildFailed) => "fill:#FF6347,stroke:#B22222",
                 Some(GNodeStatus::AncestorFailed) => "fill:#FFA07A,stroke:#FF4500",
+                Some(GNodeStatus::Cancelled) => "fill:#D3D3D3,stroke:#696969",
+                Some(GNodeStatus::TimedOut) => "fill:#FF6347,stroke:#8B0000",
                 None => "fill:#fff,stroke:#333",
             };
 
@@ -443,6 +810,7 @@ impl G {
                     Some(EdgeType::Explicit) => "-->",
                     Some(EdgeType::Scanned) => "-.->",
                     Some(EdgeType::Expanded) => "==>",
+                    Some(EdgeType::Output) => "--o",
                     None => "-->",
                 };
 
@@ -453,6 +821,7 @@ impl G {
                     Some(EdgeType::Explicit) => "#333",
                     Some(EdgeType::Scanned) => "#2196F3",
                     Some(EdgeType::Expanded) => "#FF9800",
+                    Some(EdgeType::Output) => "#9C27B0",
                     None => "#333",
                 };
                 link_styles.push(format!(
@@ -484,7 +853,7 @@ impl G {
         );
 
         println!(
-            "I:{} MC:{} MN:{} MF:{} SI:{} R:{} BS:{} BNC:{} BNR:{} BF:{} AF:{}",
+            "I:{} MC:{} MN:{} MF:{} SI:{} R:{} BS:{} BNC:{} BNR:{} BF:{} AF:{} C:{} TO:{}",
             counts
                 .get(&GNodeStatus::Initial)
                 .unwrap_or(&0)
@@ -545,6 +914,18 @@ impl G {
                 .unwrap_or(&0)
                 .to_string()
                 .truecolor(255, 165, 0)
+                .bold(),
+            counts
+                .get(&GNodeStatus::Cancelled)
+                .unwrap_or(&0)
+                .to_string()
+                .dimmed()
+                .bold(),
+            counts
+                .get(&GNodeStatus::TimedOut)
+                .unwrap_or(&0)
+                .to_string()
+                .red()
                 .bold()
         );
     }
@@ -0,0 +1,18 @@
+//! Helpers on the relative paths of nodes.
+
+use std::path::{Component, Path, PathBuf};
+
+/// Removes `.` and `dir/..` from a relative path.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if result.file_name().is_some() => {
+                result.pop();
+            }
+            c => result.push(c),
+        }
+    }
+    result
+}
@@ -0,0 +1,82 @@
+use crate::model::GNodeStatus;
+use colored::Colorize;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use petgraph::graph::NodeIndex;
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Live display of a running `make()`.
+///
+/// Shows the overall progress (done/total), one line per running node with its
+/// elapsed time, and prints failures above them as they happen.
+pub(crate) struct Progress {
+    multi: MultiProgress,
+    overall: ProgressBar,
+    running: Mutex<HashMap<NodeIndex, ProgressBar>>,
+}
+
+impl Progress {
+    /// Returns `None` when stdout is not a terminal, in which case `make()`
+    /// prints plain status lines instead.
+    pub(crate) fn new(total: u64) -> Option<Self> {
+        if !std::io::stdout().is_terminal() {
+            return None;
+        }
+        let multi = MultiProgress::with_draw_target(ProgressDrawTarget::stdout());
+        let overall = multi.add(ProgressBar::new(total));
+        overall.set_style(
+            ProgressStyle::with_template("{bar:40.cyan/blue} {pos}/{len} nodes [{elapsed}]")
+                .unwrap()
+                .progress_chars("=> "),
+        );
+        Some(Self {
+            multi,
+            overall,
+            running: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Sets the number of finished nodes and the number of nodes in the graph,
+    /// which grows when nodes are added by expand.
+    pub(crate) fn set_counts(&self, done: u64, total: u64) {
+        self.overall.set_length(total);
+        self.overall.set_position(done);
+    }
+
+    /// Adds a line for a node whose build starts.
+    pub(crate) fn start(&self, node_idx: NodeIndex, path: &Path) {
+        let bar = self.multi.add(ProgressBar::new_spinner());
+        bar.set_style(ProgressStyle::with_template("  {spinner} {prefix} [{elapsed}]").unwrap());
+        bar.set_prefix(path.display().to_string());
+        bar.enable_steady_tick(Duration::from_millis(100));
+        self.running.lock().unwrap().insert(node_idx, bar);
+    }
+
+    /// Removes the line of a node whose build ended, and prints it if it failed.
+    /// The overall position is updated by `set_counts`.
+    pub(crate) fn finish(&self, node_idx: NodeIndex, path: &Path, status: GNodeStatus) {
+        if let Some(bar) = self.running.lock().unwrap().remove(&node_idx) {
+            bar.finish_and_clear();
+            self.multi.remove(&bar);
+        }
+        if matches!(status, GNodeStatus::BuildFailed | GNodeStatus::TimedOut) {
+            let _ = self.multi.println(format!(
+                "{} {}",
+                format!("{status:?}").bright_red().bold(),
+                path.display()
+            ));
+        }
+    }
+
+    /// Removes the display, before the final status line is printed.
+    pub(crate) fn clear(&self) {
+        for (_, bar) in self.running.lock().unwrap().drain() {
+            bar.finish_and_clear();
+        }
+        self.overall.finish_and_clear();
+        let _ = self.multi.clear();
+    }
+}
@@ -154,7 +154,7 @@ impl GNode for JsonDesc {
                 lang.language
             ));
         }
-        h_content.push_str("\n");
+        h_content.push('\n');
 
         h_content.push_str(&format!("#define N_languages {}\n\n", languages.len()));
 
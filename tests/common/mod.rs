@@ -1,9 +1,14 @@
+#![allow(dead_code)]
+
 mod json_desc;
+mod shell_node;
 mod yml_desc;
 
 #[allow(unused_imports)]
 pub use json_desc::JsonDesc;
 #[allow(unused_imports)]
+pub use shell_node::{ShellNode, setup_main_c};
+#[allow(unused_imports)]
 pub use yml_desc::YmlDesc;
 
 use serde::{Deserialize, Serialize};
@@ -0,0 +1,63 @@
+use petgraph::graph::NodeIndex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+use yamake::c_nodes::CFile;
+use yamake::command::run_command;
+use yamake::model::{G, GNode};
+
+/// A node that runs a shell script, with `$OUT` set to its output path and an
+/// optional timeout.
+pub struct ShellNode {
+    pub name: String,
+    pub script: String,
+    pub timeout: Option<Duration>,
+}
+
+impl ShellNode {
+    pub fn new(name: &str, script: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            script: script.to_string(),
+            timeout: None,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+impl GNode for ShellNode {
+    fn build(&self, sandbox: &Path, _predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(&self.script);
+        cmd.env("OUT", sandbox.join(&self.name));
+        run_command(&mut cmd, sandbox, &self.name)
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn tag(&self) -> String {
+        "ShellNode".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
+
+/// A graph with the root node `p/main.c`, to which shell nodes are added.
+/// Returns the graph and the index of `p/main.c`.
+pub fn setup_main_c(srcdir: &Path, sandbox: &Path) -> (G, NodeIndex) {
+    fs::create_dir_all(srcdir.join("p")).unwrap();
+    fs::write(srcdir.join("p/main.c"), "int main() { return 0; }\n").unwrap();
+
+    let mut g = G::new(srcdir.to_path_buf(), sandbox.to_path_buf());
+    let main_c = g.add_root_node(CFile::new("p/main.c")).unwrap();
+    (g, main_c)
+}
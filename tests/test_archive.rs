@@ -0,0 +1,338 @@
+//! Test the archive nodes: TarNode and ZipNode.
+
+use flate2::read::GzDecoder;
+use std::fs::{self, File};
+use std::io::Read;
+use std::os::unix::fs::{PermissionsExt, symlink};
+use std::path::{Path, PathBuf};
+use tempdir::TempDir;
+use yamake::archive_nodes::{ArchiveLayout, TarNode, ZipNode};
+use yamake::generic_nodes::FnRootNode;
+use yamake::model::{G, GNode, GNodeStatus};
+
+/// A program, a readme and a directory of two pages, packaged in `p/app.tar.gz`,
+/// `p/app.tar` and `p/app.zip`.
+fn setup(srcdir: &Path, sandbox: &Path, doc_mode: u32) -> G {
+    let mut g = G::new(srcdir.to_path_buf(), sandbox.to_path_buf());
+    let app = g
+        .add_root_node(FnRootNode::new("p/app", "Program"))
+        .unwrap();
+    let readme = g
+        .add_root_node(FnRootNode::new("p/README.md", "Readme"))
+        .unwrap();
+    let html = g.add_node(HtmlDir).unwrap();
+    g.add_edge(readme, html);
+
+    let tgz = g
+        .add_node(
+            TarNode::new("p/app.tar.gz")
+                .with_prefix("app-1.0")
+                .with_path("p/app", "bin/app")
+                .with_path("p/README.md", "README.md")
+                .with_path("p/html", "doc")
+                .with_mode("doc/index.html", doc_mode),
+        )
+        .unwrap();
+    let zip = g
+        .add_node(
+            ZipNode::new("p/app.zip")
+                .with_prefix("app-1.0")
+                .with_path("p/app", "bin/app")
+                .with_path("p/README.md", "README.md")
+                .with_path("p/html", "doc")
+                .with_mode("doc/index.html", doc_mode),
+        )
+        .unwrap();
+    for archive in [tgz, zip] {
+        g.add_edge(app, archive);
+        g.add_edge(readme, archive);
+        g.add_edge(html, archive);
+    }
+    let tar = g
+        .add_node(
+            TarNode::new("p/app.tar")
+                .with_path("p/app", "bin/app")
+                .with_path("p/html", "doc"),
+        )
+        .unwrap();
+    g.add_edge(app, tar);
+    g.add_edge(html, tar);
+    g
+}
+
+/// Writes the pages of the readme into a directory.
+struct HtmlDir;
+
+impl GNode for HtmlDir {
+    fn build(&self, sandbox: &Path, _predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
+        let dir = sandbox.join("p/html");
+        fs::create_dir_all(&dir).unwrap();
+        let readme = fs::read_to_string(sandbox.join("p/README.md")).unwrap();
+        fs::write(dir.join("index.html"), format!("<p>{readme}</p>\n")).unwrap();
+        fs::write(dir.join("about.html"), "<p>about</p>\n").unwrap();
+        true
+    }
+
+    fn output_is_directory(&self) -> bool {
+        true
+    }
+
+    fn tag(&self) -> String {
+        "Html".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from("p/html")
+    }
+}
+
+/// The path, mode, mtime, uid and content of each entry of a tar archive.
+fn tar_entries(reader: impl Read) -> Vec<(String, u32, u64, u64, String)> {
+    let mut archive = tar::Archive::new(reader);
+    archive
+        .entries()
+        .unwrap()
+        .map(|entry| {
+            let mut entry = entry.unwrap();
+            let header = entry.header().clone();
+            let mut content = String::new();
+            entry.read_to_string(&mut content).unwrap();
+            (
+                entry.path().unwrap().to_string_lossy().to_string(),
+                header.mode().unwrap(),
+                header.mtime().unwrap(),
+                header.uid().unwrap(),
+                content,
+            )
+        })
+        .collect()
+}
+
+fn status(g: &G, path: &str) -> Option<GNodeStatus> {
+    let idx = g.node_index(Path::new(path)).unwrap();
+    g.nodes_status.get(&idx).copied()
+}
+
+/// Tests that the archives hold the outputs at their paths, sorted, with fixed
+/// dates, owner and permissions, that they are byte-identical when built again,
+/// and that a changed permission rebuilds them.
+#[test]
+fn test_archive() {
+    let srcdir = TempDir::new("yamake_archive_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_archive_sandbox").unwrap();
+    fs::create_dir_all(srcdir.path().join("p")).unwrap();
+    fs::write(srcdir.path().join("p/app"), "#!/bin/sh\necho app\n").unwrap();
+    fs::set_permissions(
+        srcdir.path().join("p/app"),
+        fs::Permissions::from_mode(0o700),
+    )
+    .unwrap();
+    fs::write(srcdir.path().join("p/README.md"), "read me").unwrap();
+
+    let mut g = setup(srcdir.path(), sandbox.path(), 0o600);
+    assert!(g.make(), "build should succeed");
+
+    let tgz = sandbox.path().join("p/app.tar.gz");
+    let entries = tar_entries(GzDecoder::new(File::open(&tgz).unwrap()));
+    let expected = [
+        ("app-1.0/README.md", 0o644, "read me"),
+        ("app-1.0/bin/app", 0o755, "#!/bin/sh\necho app\n"),
+        ("app-1.0/doc/about.html", 0o644, "<p>about</p>\n"),
+        ("app-1.0/doc/index.html", 0o600, "<p>read me</p>\n"),
+    ];
+    assert_eq!(
+        entries,
+        expected
+            .iter()
+            .map(|(p, m, c)| (p.to_string(), *m, 0, 0, c.to_string()))
+            .collect::<Vec<_>>()
+    );
+
+    let entries = tar_entries(File::open(sandbox.path().join("p/app.tar")).unwrap());
+    let paths: Vec<&str> = entries.iter().map(|e| e.0.as_str()).collect();
+    assert_eq!(paths, vec!["bin/app", "doc/about.html", "doc/index.html"]);
+
+    let mut zip =
+        zip::ZipArchive::new(File::open(sandbox.path().join("p/app.zip")).unwrap()).unwrap();
+    let mut entries = Vec::new();
+    for i in 0..zip.len() {
+        let mut file = zip.by_index(i).unwrap();
+        let mut content = String::new();
+        file.read_to_string(&mut content).unwrap();
+        let date = file.last_modified().unwrap();
+        assert_eq!((date.year(), date.month(), date.day()), (1980, 1, 1));
+        entries.push((
+            file.name().to_string(),
+            file.unix_mode().unwrap() & 0o777,
+            content,
+        ));
+    }
+    assert_eq!(
+        entries,
+        expected
+            .iter()
+            .map(|(p, m, c)| (p.to_string(), *m, c.to_string()))
+            .collect::<Vec<_>>()
+    );
+
+    // Built again from scratch, the archives are byte-identical
+    let tgz_bytes = fs::read(&tgz).unwrap();
+    let zip_bytes = fs::read(sandbox.path().join("p/app.zip")).unwrap();
+    let sandbox2 = TempDir::new("yamake_archive_sandbox2").unwrap();
+    let mut g = setup(srcdir.path(), sandbox2.path(), 0o600);
+    assert!(g.make(), "build in another sandbox should succeed");
+    assert_eq!(
+        fs::read(sandbox2.path().join("p/app.tar.gz")).unwrap(),
+        tgz_bytes
+    );
+    assert_eq!(
+        fs::read(sandbox2.path().join("p/app.zip")).unwrap(),
+        zip_bytes
+    );
+
+    // Nothing changed
+    let mut g = setup(srcdir.path(), sandbox.path(), 0o600);
+    assert!(g.make(), "second build should succeed");
+    assert_eq!(
+        status(&g, "p/app.tar.gz"),
+        Some(GNodeStatus::BuildNotRequired)
+    );
+
+    // A changed permission rebuilds the archives that set it
+    let mut g = setup(srcdir.path(), sandbox.path(), 0o644);
+    assert!(g.make(), "third build should succeed");
+    assert_eq!(status(&g, "p/app.tar.gz"), Some(GNodeStatus::BuildSuccess));
+    assert_eq!(status(&g, "p/app.zip"), Some(GNodeStatus::BuildSuccess));
+    assert_eq!(status(&g, "p/app.tar"), Some(GNodeStatus::BuildNotRequired));
+}
+
+/// Tests that two outputs at the same path in an archive fail the build.
+#[test]
+fn test_archive_duplicate_path() {
+    let srcdir = TempDir::new("yamake_archive_dup_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_archive_dup_sandbox").unwrap();
+    fs::create_dir_all(srcdir.path().join("p")).unwrap();
+    fs::write(srcdir.path().join("p/a.txt"), "a").unwrap();
+    fs::write(srcdir.path().join("p/b.txt"), "b").unwrap();
+
+    let mut g = G::new(srcdir.path().to_path_buf(), sandbox.path().to_path_buf());
+    let a = g.add_root_node(FnRootNode::new("p/a.txt", "Text")).unwrap();
+    let b = g.add_root_node(FnRootNode::new("p/b.txt", "Text")).unwrap();
+    let tar = g
+        .add_node(TarNode::new("p/texts.tar").with_path("p/b.txt", "p/a.txt"))
+        .unwrap();
+    g.add_edge(a, tar);
+    g.add_edge(b, tar);
+    assert!(!g.make(), "a duplicate path should fail");
+    assert_eq!(status(&g, "p/texts.tar"), Some(GNodeStatus::BuildFailed));
+    let stderr = fs::read_to_string(sandbox.path().join("logs/p/texts.tar.stderr")).unwrap();
+    assert!(
+        stderr.contains("p/a.txt is twice in the archive"),
+        "{stderr}"
+    );
+}
+
+/// A directory holding a library and a symbolic link to it, and, if `absolute`
+/// is set, a symbolic link to an absolute path.
+struct LibDir {
+    absolute: bool,
+}
+
+impl GNode for LibDir {
+    fn build(&self, sandbox: &Path, _predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
+        let dir = sandbox.join("p/lib");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("libfoo.so.1"), "not really a library\n").unwrap();
+        symlink("libfoo.so.1", dir.join("libfoo.so")).unwrap();
+        if self.absolute {
+            symlink("/etc/hostname", dir.join("hostname")).unwrap();
+        }
+        true
+    }
+
+    fn output_is_directory(&self) -> bool {
+        true
+    }
+
+    fn tag(&self) -> String {
+        "LibDir".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from("p/lib")
+    }
+}
+
+fn setup_links(srcdir: &Path, sandbox: &Path, absolute: bool) -> G {
+    let mut g = G::new(srcdir.to_path_buf(), sandbox.to_path_buf());
+    let version = g
+        .add_root_node(FnRootNode::new("p/version.txt", "Text"))
+        .unwrap();
+    let lib = g.add_node(LibDir { absolute }).unwrap();
+    g.add_edge(version, lib);
+    let tar = g.add_node(TarNode::new("p/lib.tar")).unwrap();
+    let zip = g.add_node(ZipNode::new("p/lib.zip")).unwrap();
+    g.add_edge(lib, tar);
+    g.add_edge(lib, zip);
+    g
+}
+
+/// Tests that the symbolic links of a directory output are archived as links,
+/// and that a link to an absolute path fails the build.
+#[test]
+fn test_archive_symlinks() {
+    let srcdir = TempDir::new("yamake_archive_links_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_archive_links_sandbox").unwrap();
+    fs::create_dir_all(srcdir.path().join("p")).unwrap();
+    fs::write(srcdir.path().join("p/version.txt"), "1\n").unwrap();
+
+    let mut g = setup_links(srcdir.path(), sandbox.path(), false);
+    assert!(g.make(), "build should succeed");
+
+    let mut archive = tar::Archive::new(File::open(sandbox.path().join("p/lib.tar")).unwrap());
+    let entries: Vec<(String, tar::EntryType, Option<PathBuf>)> = archive
+        .entries()
+        .unwrap()
+        .map(|entry| {
+            let entry = entry.unwrap();
+            (
+                entry.path().unwrap().to_string_lossy().to_string(),
+                entry.header().entry_type(),
+                entry.link_name().unwrap().map(|l| l.to_path_buf()),
+            )
+        })
+        .collect();
+    assert_eq!(
+        entries,
+        vec![
+            (
+                "p/lib/libfoo.so".to_string(),
+                tar::EntryType::Symlink,
+                Some(PathBuf::from("libfoo.so.1"))
+            ),
+            (
+                "p/lib/libfoo.so.1".to_string(),
+                tar::EntryType::Regular,
+                None
+            ),
+        ]
+    );
+
+    let mut zip =
+        zip::ZipArchive::new(File::open(sandbox.path().join("p/lib.zip")).unwrap()).unwrap();
+    let mut link = zip.by_name("p/lib/libfoo.so").unwrap();
+    assert!(link.is_symlink());
+    let mut target = String::new();
+    link.read_to_string(&mut target).unwrap();
+    assert_eq!(target, "libfoo.so.1");
+
+    let sandbox = TempDir::new("yamake_archive_links_sandbox2").unwrap();
+    let mut g = setup_links(srcdir.path(), sandbox.path(), true);
+    assert!(!g.make(), "a link to an absolute path should fail");
+    assert_eq!(status(&g, "p/lib.tar"), Some(GNodeStatus::BuildFailed));
+    let stderr = fs::read_to_string(sandbox.path().join("logs/p/lib.tar.stderr")).unwrap();
+    assert!(
+        stderr.contains("p/lib/hostname is a symbolic link to an absolute path"),
+        "{stderr}"
+    );
+}
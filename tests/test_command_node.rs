@@ -0,0 +1,175 @@
+//! Test the CommandNode, built from an argument template.
+
+use std::fs;
+use std::path::Path;
+use tempdir::TempDir;
+use yamake::c_nodes::{CFile, HFile};
+use yamake::generic_nodes::{CommandNode, load_commands};
+use yamake::model::{G, GNode, GNodeStatus, RebuildReason};
+
+/// Tests the placeholders: output, first input (not sorted), all inputs,
+/// inputs by tag, inputs inside a longer argument, and `$$`.
+#[test]
+fn test_command_node_arguments() {
+    let main_c = CFile::new("p/main.c");
+    let util_c = CFile::new("p/util.c");
+    let util_h = HFile::new("p/util.h");
+    let predecessors: Vec<&(dyn GNode + Send + Sync)> = vec![&util_h, &util_c, &main_c];
+
+    let node = CommandNode::new(
+        "p/app",
+        "XFile",
+        &[
+            "gcc",
+            "-o",
+            "$@",
+            "$^{CFile}",
+            "-DFIRST=$<",
+            "-DALL=$^",
+            "$$HOME",
+        ],
+    );
+    assert_eq!(
+        node.arguments(&predecessors).unwrap(),
+        vec![
+            "gcc",
+            "-o",
+            "p/app",
+            "p/main.c",
+            "p/util.c",
+            "-DFIRST=p/util.h",
+            "-DALL=p/main.c p/util.c p/util.h",
+            "$HOME",
+        ]
+    );
+
+    let node = CommandNode::new("p/app", "XFile", &["gcc", "$%"]);
+    assert!(node.arguments(&predecessors).is_err());
+    let node = CommandNode::new("p/app", "XFile", &["gcc", "$<"]);
+    assert!(node.arguments(&[]).is_err());
+}
+
+fn setup(srcdir: &Path, sandbox: &Path, node: CommandNode) -> G {
+    let mut g = G::new(srcdir.to_path_buf(), sandbox.to_path_buf());
+    let a = g.add_root_node(CFile::new("p/a.c")).unwrap();
+    let b = g.add_root_node(HFile::new("p/b.h")).unwrap();
+    let all = g.add_node(node).unwrap();
+    g.add_edge(a, all);
+    g.add_edge(b, all);
+    g
+}
+
+/// Tests that a node loaded from YAML runs its command in the sandbox, is not
+/// rebuilt when nothing changed, and is rebuilt when its recipe changes.
+#[test]
+fn test_command_node_build() {
+    let srcdir = TempDir::new("yamake_command_node_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_command_node_sandbox").unwrap();
+    fs::create_dir_all(srcdir.path().join("p")).unwrap();
+    fs::write(srcdir.path().join("p/a.c"), "a\n").unwrap();
+    fs::write(srcdir.path().join("p/b.h"), "b\n").unwrap();
+
+    let yaml = "name: p/all.txt\n\
+                tag: TextFile\n\
+                recipe: [sh, -c, 'cat \"$$@\" > $$0', $@, $^]\n";
+    let node: CommandNode = serde_yaml::from_str(yaml).unwrap();
+    let mut g = setup(srcdir.path(), sandbox.path(), node.clone());
+    assert!(g.make(), "build should succeed");
+    assert_eq!(
+        fs::read_to_string(sandbox.path().join("p/all.txt")).unwrap(),
+        "a\nb\n"
+    );
+
+    // Nothing changed
+    let mut g = setup(srcdir.path(), sandbox.path(), node);
+    assert!(g.make(), "second build should succeed");
+    let all = g.node_index(Path::new("p/all.txt")).unwrap();
+    assert_eq!(
+        g.nodes_status.get(&all),
+        Some(&GNodeStatus::BuildNotRequired)
+    );
+
+    // The recipe changed: only the C files
+    let node = CommandNode::new(
+        "p/all.txt",
+        "TextFile",
+        &["sh", "-c", "cat \"$$@\" > $$0", "$@", "$^{CFile}"],
+    );
+    let mut g = setup(srcdir.path(), sandbox.path(), node);
+    assert!(g.make(), "third build should succeed");
+    assert!(matches!(
+        g.explain(Path::new("p/all.txt")),
+        Some(RebuildReason::FingerprintChanged { .. })
+    ));
+    assert_eq!(
+        fs::read_to_string(sandbox.path().join("p/all.txt")).unwrap(),
+        "a\n"
+    );
+}
+
+const COMMANDS_YML: &str = "\
+- name: p/all.txt
+  tag: TextFile
+  recipe: [sh, -c, 'cat \"$$@\" > $$0', $@, $^]
+  inputs: [p/a.c, p/b.h]
+- name: p/count.txt
+  tag: TextFile
+  recipe: [sh, -c, 'wc -l < $$1 > $$0', $@, $<]
+  inputs: [p/all.txt, p/a.c]
+";
+
+/// Tests that the rules of a project file are added with their inputs, the
+/// nodes already in the graph or earlier rules, that `$<` is the first one
+/// listed, and that an unknown input or a duplicate name is an error that
+/// leaves the graph unchanged.
+#[test]
+fn test_load_commands() {
+    let srcdir = TempDir::new("yamake_load_commands_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_load_commands_sandbox").unwrap();
+    fs::create_dir_all(srcdir.path().join("p")).unwrap();
+    fs::write(srcdir.path().join("p/a.c"), "a\n").unwrap();
+    fs::write(srcdir.path().join("p/b.h"), "b\n").unwrap();
+    let commands = srcdir.path().join("p/commands.yml");
+    fs::write(&commands, COMMANDS_YML).unwrap();
+
+    let mut g = G::new(srcdir.path().to_path_buf(), sandbox.path().to_path_buf());
+    g.add_root_node(CFile::new("p/a.c")).unwrap();
+    g.add_root_node(HFile::new("p/b.h")).unwrap();
+    let indices = load_commands(&mut g, &commands).unwrap();
+    assert_eq!(indices.len(), 2);
+    assert!(g.make(), "build should succeed");
+    assert_eq!(
+        fs::read_to_string(sandbox.path().join("p/count.txt"))
+            .unwrap()
+            .trim(),
+        "2"
+    );
+
+    // The inputs must be in the graph
+    let mut g = G::new(srcdir.path().to_path_buf(), sandbox.path().to_path_buf());
+    let error = load_commands(&mut g, &commands).unwrap_err();
+    assert_eq!(error, "p/all.txt: unknown input p/a.c");
+
+    // A rule after a valid one
+    let broken = srcdir.path().join("p/broken.yml");
+    for (yaml, expected) in [
+        (
+            format!(
+                "{COMMANDS_YML}- name: p/other.txt\n  tag: TextFile\n  recipe: [touch, $@]\n  inputs: [p/missing.txt]\n"
+            ),
+            "p/other.txt: unknown input p/missing.txt",
+        ),
+        (
+            format!("{COMMANDS_YML}- name: p/all.txt\n  tag: TextFile\n  recipe: [touch, $@]\n"),
+            "Duplicate node pathbuf: \"p/all.txt\"",
+        ),
+    ] {
+        fs::write(&broken, yaml).unwrap();
+        let mut g = G::new(srcdir.path().to_path_buf(), sandbox.path().to_path_buf());
+        g.add_root_node(CFile::new("p/a.c")).unwrap();
+        g.add_root_node(HFile::new("p/b.h")).unwrap();
+        assert_eq!(load_commands(&mut g, &broken).unwrap_err(), expected);
+        assert_eq!(g.g.node_count(), 2);
+        assert_eq!(g.g.edge_count(), 0);
+    }
+}
@@ -0,0 +1,62 @@
+//! Test command timeouts in run_command.
+
+mod common;
+
+use common::{ShellNode, setup_main_c};
+use petgraph::graph::NodeIndex;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tempdir::TempDir;
+use yamake::model::{G, GNodeStatus};
+
+fn setup(srcdir: &Path, sandbox: &Path, node: ShellNode) -> (G, NodeIndex) {
+    let (mut g, main_c) = setup_main_c(srcdir, sandbox);
+    let hang = g.add_node(node).unwrap();
+    g.add_edge(main_c, hang);
+    (g, hang)
+}
+
+/// Tests that a hanging command is terminated after the node timeout, that the node
+/// is TimedOut and that the output produced before the timeout is captured.
+#[test]
+fn test_node_timeout() {
+    let srcdir = TempDir::new("yamake_timeout_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_timeout_sandbox").unwrap();
+
+    let node =
+        ShellNode::new("p/hang", "echo partial; sleep 30").with_timeout(Duration::from_millis(300));
+    let (mut g, hang) = setup(srcdir.path(), sandbox.path(), node);
+
+    let start = Instant::now();
+    assert!(!g.make(), "make should fail");
+    assert!(start.elapsed() < Duration::from_secs(10));
+
+    assert_eq!(g.nodes_status.get(&hang), Some(&GNodeStatus::TimedOut));
+
+    let stdout = fs::read_to_string(sandbox.path().join("logs/p/hang.stdout")).unwrap();
+    assert!(
+        stdout.contains("partial"),
+        "partial output should be captured"
+    );
+    let stderr = fs::read_to_string(sandbox.path().join("logs/p/hang.stderr")).unwrap();
+    assert!(stderr.contains("timed out"));
+}
+
+/// Tests that the graph timeout applies to nodes without their own timeout, and
+/// that a command ignoring SIGTERM is killed.
+#[test]
+fn test_global_timeout_kills_after_sigterm() {
+    let srcdir = TempDir::new("yamake_timeout_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_timeout_sandbox").unwrap();
+
+    let node = ShellNode::new("p/stubborn", "trap '' TERM; sleep 30");
+    let (mut g, hang) = setup(srcdir.path(), sandbox.path(), node);
+    g.timeout = Some(Duration::from_millis(300));
+
+    let start = Instant::now();
+    assert!(!g.make(), "make should fail");
+    assert!(start.elapsed() < Duration::from_secs(10));
+
+    assert_eq!(g.nodes_status.get(&hang), Some(&GNodeStatus::TimedOut));
+}
@@ -0,0 +1,111 @@
+//! Test the compilation database written from the graph.
+
+use std::fs;
+use std::path::Path;
+use tempdir::TempDir;
+use yamake::c_nodes::{CFile, HFile, OFile, XFile};
+use yamake::model::{CompileCommand, CompileCommandsPaths, G};
+
+fn setup(srcdir: &Path, sandbox: &Path) -> G {
+    fs::create_dir_all(srcdir.join("p")).unwrap();
+    fs::write(srcdir.join("p/add.h"), "int add(int a, int b);\n").unwrap();
+    fs::write(
+        srcdir.join("p/add.c"),
+        "#include \"p/add.h\"\nint add(int a, int b) { return a + b; }\n",
+    )
+    .unwrap();
+    fs::write(
+        srcdir.join("p/main.c"),
+        "#include \"p/add.h\"\nint main() { return add(1, -1); }\n",
+    )
+    .unwrap();
+
+    let mut g = G::new(srcdir.to_path_buf(), sandbox.to_path_buf());
+    let main_c = g.add_root_node(CFile::new("p/main.c")).unwrap();
+    let add_c = g.add_root_node(CFile::new("p/add.c")).unwrap();
+    g.add_root_node(HFile::new("p/add.h")).unwrap();
+    let main_o = g
+        .add_node(OFile::new("p/main.o", vec![], vec!["-O2".to_string()]))
+        .unwrap();
+    let add_o = g.add_node(OFile::new("p/add.o", vec![], vec![])).unwrap();
+    let app = g.add_node(XFile::new("p/app")).unwrap();
+    g.add_edge(main_c, main_o);
+    g.add_edge(add_c, add_o);
+    g.add_edge(main_o, app);
+    g.add_edge(add_o, app);
+    g
+}
+
+fn read_database(path: &Path) -> Vec<CompileCommand> {
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+/// Tests that make() writes one entry per OFile, with the exact command it ran.
+#[test]
+fn test_compile_commands_sandbox() {
+    let srcdir = TempDir::new("yamake_ccdb_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_ccdb_sandbox").unwrap();
+    let database = sandbox.path().join("compile_commands.json");
+
+    let mut g = setup(srcdir.path(), sandbox.path());
+    g.compile_commands = Some(database.clone());
+    assert!(g.make(), "build should succeed");
+
+    let entries = read_database(&database);
+    assert_eq!(entries.len(), 2);
+    for (entry, name) in entries.iter().zip(["add", "main"]) {
+        assert_eq!(entry.directory, sandbox.path());
+        assert_eq!(entry.file, sandbox.path().join(format!("p/{name}.c")));
+        assert_eq!(entry.output, sandbox.path().join(format!("p/{name}.o")));
+
+        // The log starts with the command that was run
+        let log =
+            fs::read_to_string(sandbox.path().join(format!("logs/p/{name}.o.stdout"))).unwrap();
+        let quoted: Vec<String> = entry.arguments.iter().map(|a| format!("{a:?}")).collect();
+        assert_eq!(log.lines().next().unwrap(), quoted.join(" "));
+    }
+    assert!(entries[1].arguments.contains(&"-O2".to_string()));
+}
+
+/// Tests that mounted sources are mapped back to the source directory, and
+/// that the sandbox stays an include path.
+#[test]
+fn test_compile_commands_srcdir() {
+    let srcdir = TempDir::new("yamake_ccdb_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_ccdb_sandbox").unwrap();
+    let database = srcdir.path().join("compile_commands.json");
+
+    let mut g = setup(srcdir.path(), sandbox.path());
+    g.compile_commands_paths = CompileCommandsPaths::Srcdir;
+    assert!(g.make(), "build should succeed");
+    g.write_compile_commands(&database).unwrap();
+
+    let entries = read_database(&database);
+    assert_eq!(entries.len(), 2);
+    let main = &entries[1];
+    assert_eq!(main.directory, srcdir.path());
+    assert_eq!(main.file, srcdir.path().join("p/main.c"));
+    assert!(
+        main.arguments
+            .contains(&main.file.to_string_lossy().to_string())
+    );
+    // The sources are found in the source directory, the generated headers
+    // still in the sandbox
+    let includes: Vec<&Path> = main
+        .arguments
+        .windows(2)
+        .filter(|w| w[0] == "-I")
+        .map(|w| Path::new(&w[1]))
+        .collect();
+    assert_eq!(includes, vec![srcdir.path(), sandbox.path()]);
+    // The object file is not a source: it stays in the sandbox
+    assert!(
+        main.arguments.contains(
+            &sandbox
+                .path()
+                .join("p/main.o")
+                .to_string_lossy()
+                .to_string()
+        )
+    );
+}
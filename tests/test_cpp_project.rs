@@ -0,0 +1,87 @@
+//! Test a mixed C/C++ project.
+
+use std::fs;
+use std::process::Command;
+use tempdir::TempDir;
+use yamake::c_nodes::{CFile, CppFile, HFile, HppFile, OFile, XFile};
+use yamake::model::{G, GNodeStatus};
+
+/// Tests that:
+/// - C++ sources are compiled with g++ and their includes are scanned
+/// - the executable is linked with the C++ driver, with the C objects
+/// - a change to a C++ header recompiles the C++ object only
+#[test]
+fn test_cpp_project() {
+    let srcdir = TempDir::new("yamake_cpp_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_cpp_sandbox").unwrap();
+    let srcdir_path = srcdir.path();
+    let sandbox_path = sandbox.path();
+
+    fs::create_dir_all(srcdir_path.join("p")).unwrap();
+    fs::write(srcdir_path.join("p/add.h"), "int add(int a, int b);\n").unwrap();
+    fs::write(
+        srcdir_path.join("p/add.c"),
+        "#include \"p/add.h\"\nint add(int a, int b) { return a + b; }\n",
+    )
+    .unwrap();
+    fs::write(
+        srcdir_path.join("p/greeting.hpp"),
+        "#include <string>\nconst std::string greeting = \"hello\";\n",
+    )
+    .unwrap();
+    fs::write(
+        srcdir_path.join("p/main.cpp"),
+        "#include \"p/greeting.hpp\"\n\
+         extern \"C\" {\n#include \"p/add.h\"\n}\n\
+         int main() { return greeting.size() == 5 && add(2, 3) == 5 ? 0 : 1; }\n",
+    )
+    .unwrap();
+
+    let mut g = G::new(srcdir_path.to_path_buf(), sandbox_path.to_path_buf());
+    let add_c = g.add_root_node(CFile::new("p/add.c")).unwrap();
+    let main_cpp = g.add_root_node(CppFile::new("p/main.cpp")).unwrap();
+    g.add_root_node(HFile::new("p/add.h")).unwrap();
+    let greeting_hpp = g.add_root_node(HppFile::new("p/greeting.hpp")).unwrap();
+    let add_o = g.add_node(OFile::new("p/add.o", vec![], vec![])).unwrap();
+    let main_o = g
+        .add_node(OFile::new_cxx("p/main.o", vec![], vec![]))
+        .unwrap();
+    let app = g.add_node(XFile::new("p/app")).unwrap();
+    g.add_edge(add_c, add_o);
+    g.add_edge(main_cpp, main_o);
+    g.add_edge(add_o, app);
+    g.add_edge(main_o, app);
+
+    assert!(g.make(), "build should succeed");
+    assert!(
+        g.g.find_edge(greeting_hpp, main_o).is_some(),
+        "greeting.hpp should be scanned"
+    );
+
+    let main_log = fs::read_to_string(sandbox_path.join("logs/p/main.o.stdout")).unwrap();
+    assert!(main_log.starts_with("\"g++\""));
+    let add_log = fs::read_to_string(sandbox_path.join("logs/p/add.o.stdout")).unwrap();
+    assert!(add_log.starts_with("\"gcc\""));
+    let app_log = fs::read_to_string(sandbox_path.join("logs/p/app.stdout")).unwrap();
+    assert!(app_log.starts_with("\"g++\""));
+
+    let status = Command::new(sandbox_path.join("p/app")).status().unwrap();
+    assert!(status.success(), "app should return 0");
+
+    // Changing the C++ header only recompiles main.o
+    fs::write(
+        srcdir_path.join("p/greeting.hpp"),
+        "#include <string>\nconst std::string greeting = \"hallo\";\n",
+    )
+    .unwrap();
+    assert!(g.make(), "second build should succeed");
+    assert_eq!(
+        g.nodes_status.get(&main_o),
+        Some(&GNodeStatus::BuildSuccess)
+    );
+    assert_eq!(
+        g.nodes_status.get(&add_o),
+        Some(&GNodeStatus::BuildNotRequired)
+    );
+    assert_eq!(g.nodes_status.get(&app), Some(&GNodeStatus::BuildSuccess));
+}
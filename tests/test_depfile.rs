@@ -0,0 +1,100 @@
+//! Test the make-format depfile parser.
+
+use std::fs;
+use std::path::PathBuf;
+use tempdir::TempDir;
+use yamake::depfile::{self, Rule};
+
+/// Tests parsing of continuations, escaped spaces, `$$`, comments and several
+/// targets per rule.
+#[test]
+fn test_parse_depfile() {
+    let content = "# generated\n\
+                   main.o main.d: main.c \\\n  include/my\\ header.h \\\n  price$$.h\n\
+                   \n\
+                   include/my\\ header.h:\n";
+
+    let rules = depfile::parse(content);
+    assert_eq!(
+        rules,
+        vec![
+            Rule {
+                targets: vec![PathBuf::from("main.o"), PathBuf::from("main.d")],
+                prerequisites: vec![
+                    PathBuf::from("main.c"),
+                    PathBuf::from("include/my header.h"),
+                    PathBuf::from("price$.h"),
+                ],
+            },
+            Rule {
+                targets: vec![PathBuf::from("include/my header.h")],
+                prerequisites: vec![],
+            },
+        ]
+    );
+
+    assert_eq!(
+        depfile::prerequisites(content),
+        vec![
+            PathBuf::from("main.c"),
+            PathBuf::from("include/my header.h"),
+            PathBuf::from("price$.h"),
+        ]
+    );
+}
+
+/// Tests that `scan` makes prerequisites relative to the sandbox, drops those
+/// outside of it, and returns a complete empty scan when the depfile is missing.
+#[test]
+fn test_scan_depfile() {
+    let sandbox = TempDir::new("yamake_depfile_scan").unwrap();
+    let sandbox_path = sandbox.path().to_path_buf();
+
+    let content = format!(
+        "{sb}/p/main.o: {sb}/p/main.c {sb}/p/main.h /usr/include/stdio.h p/gen.h\n",
+        sb = sandbox_path.display()
+    );
+    fs::create_dir_all(sandbox_path.join("p")).unwrap();
+    fs::write(sandbox_path.join("p/main.d"), content).unwrap();
+
+    let (complete, deps) = depfile::scan(&sandbox_path, &PathBuf::from("p/main.d"));
+    assert!(complete);
+    assert_eq!(
+        deps,
+        vec![
+            PathBuf::from("p/main.c"),
+            PathBuf::from("p/main.h"),
+            PathBuf::from("p/gen.h"),
+        ]
+    );
+
+    let (complete, deps) = depfile::scan(&sandbox_path, &PathBuf::from("p/missing.d"));
+    assert!(complete);
+    assert!(deps.is_empty());
+}
+
+/// Tests that `relativize` removes `.` and `..` so that the paths match the
+/// nodes, and that `normalize_paths` keeps the headers outside the sandbox.
+#[test]
+fn test_normalize_depfile_paths() {
+    let sandbox = PathBuf::from("/tmp/sandbox");
+    let paths = vec![
+        PathBuf::from("/tmp/sandbox/p/../include/add.h"),
+        PathBuf::from("./p/main.h"),
+        PathBuf::from("p/sub/../main.h"),
+        PathBuf::from("/usr/include/../include/stdio.h"),
+    ];
+
+    assert_eq!(
+        depfile::relativize(&sandbox, paths.clone()),
+        vec![PathBuf::from("include/add.h"), PathBuf::from("p/main.h")]
+    );
+    assert_eq!(
+        depfile::normalize_paths(&sandbox, paths),
+        vec![
+            PathBuf::from("include/add.h"),
+            PathBuf::from("p/main.h"),
+            PathBuf::from("/usr/include/stdio.h"),
+        ]
+    );
+}
@@ -0,0 +1,75 @@
+//! Test that AFile builds deterministic static archives.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+use tempdir::TempDir;
+use yamake::c_nodes::{AFile, CFile, OFile, XFile};
+use yamake::model::{G, GNodeStatus};
+
+/// `p/app` linking `p/libproject.a`, whose objects are given in reverse order.
+fn setup(srcdir: &Path, sandbox: &Path) -> G {
+    let mut g = G::new(srcdir.to_path_buf(), sandbox.to_path_buf());
+    let lib = g.add_node(AFile::new("p/libproject.a")).unwrap();
+    for name in ["sub", "add"] {
+        let c = g.add_root_node(CFile::new(&format!("p/{name}.c"))).unwrap();
+        let o = g
+            .add_node(OFile::new(&format!("p/{name}.o"), vec![], vec![]))
+            .unwrap();
+        g.add_edge(c, o);
+        g.add_edge(o, lib);
+    }
+    let main_c = g.add_root_node(CFile::new("p/main.c")).unwrap();
+    let main_o = g.add_node(OFile::new("p/main.o", vec![], vec![])).unwrap();
+    let app = g.add_node(XFile::new("p/app")).unwrap();
+    g.add_edge(main_c, main_o);
+    g.add_edge(main_o, app);
+    g.add_edge(lib, app);
+    g
+}
+
+/// Tests that an archive built again from the same objects is identical, so
+/// that it is `BuildNotChanged` and the executable is not linked again, and
+/// that its members are sorted by path.
+#[test]
+fn test_deterministic_archive() {
+    let srcdir = TempDir::new("yamake_deterministic_archive_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_deterministic_archive_sandbox").unwrap();
+    fs::create_dir_all(srcdir.path().join("p")).unwrap();
+    fs::write(
+        srcdir.path().join("p/add.c"),
+        "int add(int a, int b) { return a + b; }\n",
+    )
+    .unwrap();
+    fs::write(
+        srcdir.path().join("p/sub.c"),
+        "int sub(int a, int b) { return a - b; }\n",
+    )
+    .unwrap();
+    fs::write(
+        srcdir.path().join("p/main.c"),
+        "int add(int, int);\nint sub(int, int);\nint main(void) { return sub(add(1, 2), 3); }\n",
+    )
+    .unwrap();
+
+    let mut g = setup(srcdir.path(), sandbox.path());
+    assert!(g.make(), "build should succeed");
+
+    let lib = sandbox.path().join("p/libproject.a");
+    let output = Command::new("ar").arg("t").arg(&lib).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "add.o\nsub.o\n");
+
+    // A timestamp would differ from the first build
+    thread::sleep(Duration::from_millis(1100));
+    fs::remove_file(&lib).unwrap();
+    let mut g = setup(srcdir.path(), sandbox.path());
+    assert!(g.make(), "second build should succeed");
+    let status = |path: &str| {
+        let idx = g.node_index(Path::new(path)).unwrap();
+        g.nodes_status.get(&idx).copied()
+    };
+    assert_eq!(status("p/libproject.a"), Some(GNodeStatus::BuildNotChanged));
+    assert_eq!(status("p/app"), Some(GNodeStatus::BuildNotRequired));
+}
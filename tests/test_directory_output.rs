@@ -0,0 +1,250 @@
+//! Test nodes whose output is a directory: stale files are removed before a
+//! rebuild, the output must be a directory, and its Merkle digest only changes
+//! with its content.
+
+use petgraph::graph::NodeIndex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempdir::TempDir;
+use yamake::model::{G, GNode, GNodeStatus, GRootNode, MakeOutput};
+
+/// A specification: one `path: content` line per generated file, `#` comments.
+struct SpecFile {
+    name: String,
+}
+
+impl GRootNode for SpecFile {
+    fn tag(&self) -> String {
+        "SpecFile".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
+
+/// A generator writing the files of its specification into its directory.
+struct GenDir {
+    name: String,
+    /// Writes a regular file instead of a directory.
+    buggy: bool,
+}
+
+impl GNode for GenDir {
+    fn build(&self, sandbox: &Path, predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
+        let output = sandbox.join(&self.name);
+        if self.buggy {
+            let _ = fs::remove_dir_all(&output);
+            return fs::write(&output, "not a directory\n").is_ok();
+        }
+        let Some(spec) = predecessors.iter().find(|p| p.tag() == "SpecFile") else {
+            return false;
+        };
+        let content = fs::read_to_string(sandbox.join(spec.pathbuf())).unwrap();
+        for line in content.lines().filter(|l| !l.starts_with('#')) {
+            let Some((path, text)) = line.split_once(": ") else {
+                continue;
+            };
+            let path = output.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+        true
+    }
+
+    fn output_is_directory(&self) -> bool {
+        true
+    }
+
+    fn tag(&self) -> String {
+        "GenDir".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
+
+/// Lists the files of the generated directory.
+struct Listing {
+    name: String,
+}
+
+impl GNode for Listing {
+    fn build(&self, sandbox: &Path, predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
+        let mut names: Vec<String> = Vec::new();
+        for pred in predecessors {
+            for entry in fs::read_dir(sandbox.join(pred.pathbuf())).unwrap() {
+                names.push(entry.unwrap().file_name().to_string_lossy().to_string());
+            }
+        }
+        names.sort();
+        fs::write(sandbox.join(&self.name), names.join("\n")).is_ok()
+    }
+
+    fn tag(&self) -> String {
+        "Listing".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
+
+fn setup(srcdir: &Path, sandbox: &Path, buggy: bool) -> (G, NodeIndex, NodeIndex) {
+    let mut g = G::new(srcdir.to_path_buf(), sandbox.to_path_buf());
+    let spec = g
+        .add_root_node(SpecFile {
+            name: "gen/spec.txt".to_string(),
+        })
+        .unwrap();
+    let gen_dir = g
+        .add_node(GenDir {
+            name: "gen/out".to_string(),
+            buggy,
+        })
+        .unwrap();
+    let listing = g
+        .add_node(Listing {
+            name: "gen/listing.txt".to_string(),
+        })
+        .unwrap();
+    g.add_edge(spec, gen_dir);
+    g.add_edge(gen_dir, listing);
+    (g, gen_dir, listing)
+}
+
+fn report_digest(sandbox: &Path, path: &str) -> Option<String> {
+    let report = fs::File::open(sandbox.join("make-report.yml")).unwrap();
+    let output: MakeOutput = serde_yaml::from_reader(report).unwrap();
+    output
+        .nodes
+        .into_iter()
+        .find(|n| n.pathbuf == Path::new(path))
+        .unwrap()
+        .digest
+}
+
+/// Tests that:
+/// - the directory is built and digested
+/// - nothing is rebuilt when nothing changed
+/// - a file no longer generated is removed by the rebuild
+/// - a spec change that generates the same content leaves the digest unchanged
+/// - a node declaring a directory output fails when it writes a file, and its
+///   digest is not recorded
+#[test]
+fn test_directory_output() {
+    let srcdir = TempDir::new("yamake_dir_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_dir_sandbox").unwrap();
+    fs::create_dir_all(srcdir.path().join("gen")).unwrap();
+    let spec_path = srcdir.path().join("gen/spec.txt");
+    fs::write(&spec_path, "a.txt: a\nb.txt: b\nsub/c.txt: c\n").unwrap();
+
+    let (mut g, _, _) = setup(srcdir.path(), sandbox.path(), false);
+    assert!(g.make(), "build should succeed");
+    let out = sandbox.path().join("gen/out");
+    assert!(out.join("sub/c.txt").exists());
+    assert_eq!(
+        fs::read_to_string(sandbox.path().join("gen/listing.txt")).unwrap(),
+        "a.txt\nb.txt\nsub"
+    );
+    let digest = report_digest(sandbox.path(), "gen/out");
+    assert!(digest.is_some(), "directory should be digested");
+
+    // Nothing changed
+    let (mut g, gen_dir2, listing2) = setup(srcdir.path(), sandbox.path(), false);
+    assert!(g.make(), "second build should succeed");
+    assert_eq!(
+        g.nodes_status.get(&gen_dir2),
+        Some(&GNodeStatus::BuildNotRequired)
+    );
+    assert_eq!(
+        g.nodes_status.get(&listing2),
+        Some(&GNodeStatus::BuildNotRequired)
+    );
+
+    // b.txt is no longer generated: the stale file is removed
+    fs::write(&spec_path, "a.txt: a\nsub/c.txt: c\n").unwrap();
+    let (mut g, gen_dir3, listing3) = setup(srcdir.path(), sandbox.path(), false);
+    assert!(g.make(), "third build should succeed");
+    assert_eq!(
+        g.nodes_status.get(&gen_dir3),
+        Some(&GNodeStatus::BuildSuccess)
+    );
+    assert_eq!(
+        g.nodes_status.get(&listing3),
+        Some(&GNodeStatus::BuildSuccess)
+    );
+    assert!(!out.join("b.txt").exists(), "stale file should be removed");
+    assert_ne!(report_digest(sandbox.path(), "gen/out"), digest);
+    let digest = report_digest(sandbox.path(), "gen/out");
+
+    // Only a comment changed: same content, same Merkle digest
+    fs::write(&spec_path, "# generated files\na.txt: a\nsub/c.txt: c\n").unwrap();
+    let (mut g, gen_dir4, listing4) = setup(srcdir.path(), sandbox.path(), false);
+    assert!(g.make(), "fourth build should succeed");
+    assert_eq!(
+        g.nodes_status.get(&gen_dir4),
+        Some(&GNodeStatus::BuildNotChanged)
+    );
+    assert_eq!(
+        g.nodes_status.get(&listing4),
+        Some(&GNodeStatus::BuildNotRequired)
+    );
+    assert_eq!(report_digest(sandbox.path(), "gen/out"), digest);
+
+    // A regular file where a directory is declared
+    fs::write(&spec_path, "a.txt: A\n").unwrap();
+    let (mut g, gen_dir5, _) = setup(srcdir.path(), sandbox.path(), true);
+    assert!(!g.make(), "a file output should fail");
+    assert_eq!(
+        g.nodes_status.get(&gen_dir5),
+        Some(&GNodeStatus::BuildFailed)
+    );
+    assert_eq!(report_digest(sandbox.path(), "gen/out"), None);
+
+    // What the failed node left is not taken as up to date
+    let (mut g, gen_dir6, _) = setup(srcdir.path(), sandbox.path(), false);
+    assert!(g.make(), "sixth build should succeed");
+    assert_eq!(
+        g.nodes_status.get(&gen_dir6),
+        Some(&GNodeStatus::BuildSuccess)
+    );
+    assert!(out.join("a.txt").exists());
+}
+
+/// Tests that a directory output whose pathbuf is not inside the sandbox
+/// fails without removing anything.
+#[test]
+fn test_directory_output_outside_sandbox() {
+    let srcdir = TempDir::new("yamake_dir_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_dir_sandbox").unwrap();
+    let outside = TempDir::new("yamake_dir_outside").unwrap();
+    fs::write(outside.path().join("keep.txt"), "keep\n").unwrap();
+    fs::create_dir_all(srcdir.path().join("gen")).unwrap();
+    fs::write(srcdir.path().join("gen/spec.txt"), "a.txt: a\n").unwrap();
+
+    let outside_name = outside.path().to_string_lossy().to_string();
+    for name in ["", ".", "gen/..", "..", outside_name.as_str()] {
+        let mut g = G::new(srcdir.path().to_path_buf(), sandbox.path().to_path_buf());
+        let spec = g
+            .add_root_node(SpecFile {
+                name: "gen/spec.txt".to_string(),
+            })
+            .unwrap();
+        let gen_dir = g
+            .add_node(GenDir {
+                name: name.to_string(),
+                buggy: false,
+            })
+            .unwrap();
+        g.add_edge(spec, gen_dir);
+        assert!(!g.make(), "{name:?} should fail");
+        assert_eq!(
+            g.nodes_status.get(&gen_dir),
+            Some(&GNodeStatus::BuildFailed)
+        );
+        assert!(sandbox.path().join("gen/spec.txt").exists());
+        assert!(outside.path().join("keep.txt").exists());
+    }
+}
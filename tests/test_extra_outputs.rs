@@ -0,0 +1,211 @@
+//! Test nodes with extra outputs: a generator writing a source and its header.
+
+use petgraph::graph::NodeIndex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempdir::TempDir;
+use yamake::c_nodes::{CFile, OFile, XFile};
+use yamake::model::{
+    G, GNode, GNodeStatus, GRootNode, GraphError, MakeOutput, OutputFile, RebuildReason,
+};
+
+/// The value returned by the generated function.
+struct ValueFile {
+    name: String,
+}
+
+impl GRootNode for ValueFile {
+    fn tag(&self) -> String {
+        "ValueFile".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
+
+/// Generates `foo.c` and, as an extra output, `foo.h`.
+struct GenFoo {
+    name: String,
+    /// Forgets to write the header.
+    buggy: bool,
+}
+
+impl GenFoo {
+    fn header(&self) -> PathBuf {
+        PathBuf::from(&self.name).with_extension("h")
+    }
+}
+
+impl GNode for GenFoo {
+    fn build(&self, sandbox: &Path, predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
+        let Some(value) = predecessors.iter().find(|p| p.tag() == "ValueFile") else {
+            return false;
+        };
+        let value = fs::read_to_string(sandbox.join(value.pathbuf())).unwrap();
+        fs::write(
+            sandbox.join(&self.name),
+            format!(
+                "#include \"p/foo.h\"\nint foo(void) {{ return {}; }}\n",
+                value.trim()
+            ),
+        )
+        .unwrap();
+        if !self.buggy {
+            fs::write(sandbox.join(self.header()), "int foo(void);\n").unwrap();
+        }
+        true
+    }
+
+    fn outputs(&self) -> Vec<OutputFile> {
+        vec![OutputFile::new(&self.header().to_string_lossy(), "HFile")]
+    }
+
+    fn tag(&self) -> String {
+        "CFile".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
+
+const MAIN_C: &str = "#include <stdio.h>\n#include \"p/foo.h\"\n\
+int main(void) { printf(\"%d\\n\", foo()); return 0; }\n";
+
+struct Nodes {
+    foo_h: NodeIndex,
+    foo_o: NodeIndex,
+    main_o: NodeIndex,
+}
+
+fn setup(srcdir: &Path, sandbox: &Path, buggy: bool) -> (G, Nodes) {
+    let mut g = G::new(srcdir.to_path_buf(), sandbox.to_path_buf());
+    let value = g
+        .add_root_node(ValueFile {
+            name: "p/value.txt".to_string(),
+        })
+        .unwrap();
+    let main_c = g.add_root_node(CFile::new("p/main.c")).unwrap();
+    let foo_c = g
+        .add_node(GenFoo {
+            name: "p/foo.c".to_string(),
+            buggy,
+        })
+        .unwrap();
+    let foo_h = g.node_index(Path::new("p/foo.h")).unwrap();
+    let foo_o = g.add_node(OFile::new("p/foo.o", vec![], vec![])).unwrap();
+    let main_o = g.add_node(OFile::new("p/main.o", vec![], vec![])).unwrap();
+    let app = g.add_node(XFile::new("p/app")).unwrap();
+    g.add_edge(value, foo_c);
+    g.add_edge(foo_c, foo_o);
+    g.add_edge(foo_h, foo_o);
+    g.add_edge(main_c, main_o);
+    g.add_edge(foo_h, main_o);
+    g.add_edge(foo_o, app);
+    g.add_edge(main_o, app);
+    (
+        g,
+        Nodes {
+            foo_h,
+            foo_o,
+            main_o,
+        },
+    )
+}
+
+fn report_digest(sandbox: &Path, path: &str) -> Option<String> {
+    let report = fs::File::open(sandbox.join("make-report.yml")).unwrap();
+    let output: MakeOutput = serde_yaml::from_reader(report).unwrap();
+    output
+        .nodes
+        .into_iter()
+        .find(|n| n.pathbuf == Path::new(path))
+        .unwrap()
+        .digest
+}
+
+/// Tests that:
+/// - the header is a node other nodes depend on, digested on its own
+/// - a deleted extra output rebuilds its producer
+/// - a change of the source only does not rebuild the objects depending on the header
+/// - a build that does not write an extra output fails
+#[test]
+fn test_extra_outputs() {
+    let srcdir = TempDir::new("yamake_outputs_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_outputs_sandbox").unwrap();
+    fs::create_dir_all(srcdir.path().join("p")).unwrap();
+    fs::write(srcdir.path().join("p/main.c"), MAIN_C).unwrap();
+    fs::write(srcdir.path().join("p/value.txt"), "42\n").unwrap();
+
+    let (mut g, _) = setup(srcdir.path(), sandbox.path(), false);
+    assert!(g.make(), "build should succeed");
+    let output = Command::new(sandbox.path().join("p/app")).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "42\n");
+    let foo_c_digest = report_digest(sandbox.path(), "p/foo.c");
+    let foo_h_digest = report_digest(sandbox.path(), "p/foo.h");
+    assert!(foo_h_digest.is_some());
+    assert_ne!(foo_c_digest, foo_h_digest);
+
+    // The extra output was deleted: its producer is built again
+    fs::remove_file(sandbox.path().join("p/foo.h")).unwrap();
+    let (mut g, nodes) = setup(srcdir.path(), sandbox.path(), false);
+    assert!(g.make(), "second build should succeed");
+    assert_eq!(
+        g.explain(Path::new("p/foo.c")),
+        Some(&RebuildReason::OutputMissing)
+    );
+    assert!(sandbox.path().join("p/foo.h").exists());
+    assert_eq!(
+        g.nodes_status.get(&nodes.foo_h),
+        Some(&GNodeStatus::BuildNotRequired)
+    );
+    assert_eq!(
+        g.nodes_status.get(&nodes.main_o),
+        Some(&GNodeStatus::BuildNotRequired)
+    );
+
+    // Only the source changes: the header is not changed
+    fs::write(srcdir.path().join("p/value.txt"), "7\n").unwrap();
+    let (mut g, nodes) = setup(srcdir.path(), sandbox.path(), false);
+    assert!(g.make(), "third build should succeed");
+    assert_eq!(
+        g.nodes_status.get(&nodes.foo_h),
+        Some(&GNodeStatus::BuildNotChanged)
+    );
+    assert_eq!(
+        g.nodes_status.get(&nodes.foo_o),
+        Some(&GNodeStatus::BuildSuccess)
+    );
+    assert_eq!(
+        g.nodes_status.get(&nodes.main_o),
+        Some(&GNodeStatus::BuildNotRequired)
+    );
+    assert_eq!(report_digest(sandbox.path(), "p/foo.h"), foo_h_digest);
+    let output = Command::new(sandbox.path().join("p/app")).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "7\n");
+
+    // The header is not written
+    fs::remove_file(sandbox.path().join("p/foo.h")).unwrap();
+    let (mut g, nodes) = setup(srcdir.path(), sandbox.path(), true);
+    assert!(!g.make(), "a missing extra output should fail");
+    let foo_c = g.node_index(Path::new("p/foo.c")).unwrap();
+    assert_eq!(g.nodes_status.get(&foo_c), Some(&GNodeStatus::BuildFailed));
+    assert_eq!(
+        g.nodes_status.get(&nodes.foo_h),
+        Some(&GNodeStatus::AncestorFailed)
+    );
+}
+
+/// Tests that an extra output cannot have the pathbuf of another node.
+#[test]
+fn test_extra_output_duplicate() {
+    let mut g = G::new(PathBuf::from("src"), PathBuf::from("sandbox"));
+    g.add_root_node(CFile::new("p/foo.h")).unwrap();
+    let result = g.add_node(GenFoo {
+        name: "p/foo.c".to_string(),
+        buggy: false,
+    });
+    assert!(matches!(result, Err(GraphError::DuplicatePathBuf(p)) if p == Path::new("p/foo.h")));
+}
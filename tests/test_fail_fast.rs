@@ -0,0 +1,79 @@
+//! Test fail-fast and keep-going make modes.
+
+mod common;
+
+use common::{ShellNode, setup_main_c};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tempdir::TempDir;
+use yamake::model::{G, GNodeStatus, MakeMode};
+
+/// Builds the graph:
+///
+/// ```text
+/// main.c ──► fail
+/// main.c ──► slow ──► after
+/// ```
+fn setup(srcdir: &Path, sandbox: &Path, mode: MakeMode) -> G {
+    let (mut g, main_c) = setup_main_c(srcdir, sandbox);
+    g.mode = mode;
+
+    let fail = g.add_node(ShellNode::new("p/fail", "exit 1")).unwrap();
+    let slow = g
+        .add_node(ShellNode::new("p/slow", "sleep 3 && touch \"$OUT\""))
+        .unwrap();
+    let after = g
+        .add_node(ShellNode::new("p/after", "touch \"$OUT\""))
+        .unwrap();
+    g.add_edge(main_c, fail);
+    g.add_edge(main_c, slow);
+    g.add_edge(slow, after);
+    g
+}
+
+fn status_of(g: &G, path: &str) -> Option<GNodeStatus> {
+    let idx =
+        g.g.node_indices()
+            .find(|&idx| g.g[idx].pathbuf() == Path::new(path))?;
+    g.nodes_status.get(&idx).copied()
+}
+
+/// In fail-fast mode, the first failure stops the make: nodes that were not
+/// started are Cancelled, and running commands are killed.
+#[test]
+fn test_fail_fast_cancels_pending_nodes() {
+    let srcdir = TempDir::new("yamake_failfast_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_failfast_sandbox").unwrap();
+
+    let mut g = setup(srcdir.path(), sandbox.path(), MakeMode::FailFast);
+
+    let start = Instant::now();
+    let result = g.make();
+    assert!(!result, "make should fail");
+
+    assert_eq!(status_of(&g, "p/fail"), Some(GNodeStatus::BuildFailed));
+    assert_eq!(status_of(&g, "p/after"), Some(GNodeStatus::Cancelled));
+    assert!(!sandbox.path().join("p/after").exists());
+
+    // With more than one worker, slow runs alongside fail and gets killed
+    if rayon::current_num_threads() > 1 {
+        assert_eq!(status_of(&g, "p/slow"), Some(GNodeStatus::Cancelled));
+        assert!(start.elapsed() < Duration::from_secs(3));
+    }
+}
+
+/// In keep-going mode, nodes that do not depend on the failure are built.
+#[test]
+fn test_keep_going_builds_independent_nodes() {
+    let srcdir = TempDir::new("yamake_keepgoing_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_keepgoing_sandbox").unwrap();
+
+    let mut g = setup(srcdir.path(), sandbox.path(), MakeMode::KeepGoing);
+
+    let result = g.make();
+    assert!(!result, "make should fail");
+
+    assert_eq!(status_of(&g, "p/fail"), Some(GNodeStatus::BuildFailed));
+    assert_eq!(status_of(&g, "p/slow"), Some(GNodeStatus::BuildSuccess));
+    assert_eq!(status_of(&g, "p/after"), Some(GNodeStatus::BuildSuccess));
+}
@@ -0,0 +1,122 @@
+//! Test the closure-based nodes: FnNode and FnRootNode.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempdir::TempDir;
+use yamake::generic_nodes::{FnNode, FnRootNode};
+use yamake::model::{Edge, G, GNode, GNodeStatus};
+
+fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Counts the words of the `Words` predecessor, and of `p/extra.txt`, found by scan.
+fn count_node() -> FnNode {
+    FnNode::new("p/count.txt", "Count", |ctx| {
+        let mut count = read(&ctx.input("Words")?)?.split_whitespace().count();
+        for extra in ctx.inputs("Extra") {
+            count += read(&extra)?.split_whitespace().count();
+        }
+        ctx.log(&format!("{count} words"));
+        fs::write(ctx.output(), count.to_string()).map_err(|e| e.to_string())
+    })
+    .with_scan(|ctx| {
+        let extra = PathBuf::from("p/extra.txt");
+        let found = ctx.sandbox.join(&extra).exists();
+        (true, if found { vec![extra] } else { vec![] })
+    })
+}
+
+/// Writes a greeting for each name of the root node.
+fn names_node() -> FnRootNode {
+    FnRootNode::new("p/names.txt", "Names").with_expand(|ctx| {
+        let names = fs::read_to_string(ctx.sandbox.join(ctx.name)).unwrap_or_default();
+        let mut nodes: Vec<Box<dyn GNode + Send + Sync>> = Vec::new();
+        let mut edges = Vec::new();
+        for name in names.split_whitespace() {
+            let path = format!("p/hello_{name}.txt");
+            let greeting = format!("hello {name}\n");
+            nodes.push(Box::new(FnNode::new(&path, "Greeting", move |ctx| {
+                fs::write(ctx.output(), &greeting).map_err(|e| e.to_string())
+            })));
+            edges.push(Edge {
+                nfrom: Box::new(FnRootNode::new("p/names.txt", "Names")),
+                nto: Box::new(FnNode::new(&path, "Greeting", |_| Ok(()))),
+            });
+        }
+        Ok((nodes, edges))
+    })
+}
+
+fn setup(srcdir: &Path, sandbox: &Path) -> G {
+    let mut g = G::new(srcdir.to_path_buf(), sandbox.to_path_buf());
+    let words = g
+        .add_root_node(FnRootNode::new("p/words.txt", "Words"))
+        .unwrap();
+    g.add_root_node(FnRootNode::new("p/extra.txt", "Extra"))
+        .unwrap();
+    g.add_root_node(names_node()).unwrap();
+    let count = g.add_node(count_node()).unwrap();
+    g.add_edge(words, count);
+    g
+}
+
+/// Tests that:
+/// - the build closure gets its inputs by tag, and its log lines are in the logs
+/// - the scan closure adds a dependency that rebuilds the node when it changes
+/// - the expand closure adds nodes built by closures
+/// - an error returned by the build closure fails the node and is in the logs
+#[test]
+fn test_fn_node() {
+    let srcdir = TempDir::new("yamake_fn_node_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_fn_node_sandbox").unwrap();
+    fs::create_dir_all(srcdir.path().join("p")).unwrap();
+    fs::write(srcdir.path().join("p/words.txt"), "one two three\n").unwrap();
+    fs::write(srcdir.path().join("p/extra.txt"), "four\n").unwrap();
+    fs::write(srcdir.path().join("p/names.txt"), "ada alan\n").unwrap();
+
+    let mut g = setup(srcdir.path(), sandbox.path());
+    assert!(g.make(), "build should succeed");
+    assert_eq!(
+        fs::read_to_string(sandbox.path().join("p/count.txt")).unwrap(),
+        "4"
+    );
+    let stdout = fs::read_to_string(sandbox.path().join("logs/p/count.txt.stdout")).unwrap();
+    assert_eq!(stdout, "FnNode p/count.txt\n4 words\n");
+    assert_eq!(
+        fs::read_to_string(sandbox.path().join("p/hello_alan.txt")).unwrap(),
+        "hello alan\n"
+    );
+    assert!(sandbox.path().join("p/hello_ada.txt").exists());
+
+    // Nothing changed
+    let mut g = setup(srcdir.path(), sandbox.path());
+    assert!(g.make(), "second build should succeed");
+    let count = g.node_index(Path::new("p/count.txt")).unwrap();
+    assert_eq!(
+        g.nodes_status.get(&count),
+        Some(&GNodeStatus::BuildNotRequired)
+    );
+
+    // The scanned dependency changed
+    fs::write(srcdir.path().join("p/extra.txt"), "four five\n").unwrap();
+    let mut g = setup(srcdir.path(), sandbox.path());
+    assert!(g.make(), "third build should succeed");
+    assert_eq!(
+        fs::read_to_string(sandbox.path().join("p/count.txt")).unwrap(),
+        "5"
+    );
+
+    // The build closure returns an error
+    let sandbox = TempDir::new("yamake_fn_node_sandbox").unwrap();
+    let mut g = G::new(srcdir.path().to_path_buf(), sandbox.path().to_path_buf());
+    let extra = g
+        .add_root_node(FnRootNode::new("p/extra.txt", "Extra"))
+        .unwrap();
+    let count = g.add_node(count_node()).unwrap();
+    g.add_edge(extra, count);
+    assert!(!g.make(), "build without Words should fail");
+    assert_eq!(g.nodes_status.get(&count), Some(&GNodeStatus::BuildFailed));
+    let stderr = fs::read_to_string(sandbox.path().join("logs/p/count.txt.stderr")).unwrap();
+    assert!(stderr.contains("p/count.txt: expected one Words predecessor, found 0"));
+}
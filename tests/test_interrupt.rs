@@ -0,0 +1,36 @@
+//! Test interrupting make() from the interrupt handler of a program.
+//!
+//! `interrupt_all` cancels every sandbox of the process, so this test has a
+//! binary of its own.
+
+mod common;
+
+use common::{ShellNode, setup_main_c};
+use std::thread;
+use std::time::{Duration, Instant};
+use tempdir::TempDir;
+use yamake::command::interrupt_all;
+use yamake::model::GNodeStatus;
+
+/// Tests that `interrupt_all` terminates the running command, cancels the
+/// build and that the report is still written.
+#[test]
+fn test_interrupt_all() {
+    let srcdir = TempDir::new("yamake_interrupt_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_interrupt_sandbox").unwrap();
+    let (mut g, main_c) = setup_main_c(srcdir.path(), sandbox.path());
+    let hang = g.add_node(ShellNode::new("p/hang", "sleep 30")).unwrap();
+    g.add_edge(main_c, hang);
+
+    let interrupter = thread::spawn(|| {
+        thread::sleep(Duration::from_millis(300));
+        interrupt_all();
+    });
+    let start = Instant::now();
+    assert!(!g.make(), "make should fail");
+    assert!(start.elapsed() < Duration::from_secs(10));
+    interrupter.join().unwrap();
+
+    assert_eq!(g.nodes_status.get(&hang), Some(&GNodeStatus::Cancelled));
+    assert!(sandbox.path().join("make-report.yml").exists());
+}
@@ -0,0 +1,137 @@
+//! Test the LaTeX nodes: scan of a document and reruns of the engine.
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use tempdir::TempDir;
+use yamake::latex_nodes::{PdfFile, TexFile, TikzFile};
+use yamake::model::{G, GNode, GNodeStatus};
+
+const MAIN_TEX: &str = "\\documentclass{article}\n\
+\\newcommand{\\songly}[1]{\\subimport{#1.output}{#1}}\n\
+\\begin{document}\n\
+\\input{data.tex}\n\
+\\input{projectile.tikz}\n\
+% \\input{commented}\n\
+\\include{chapter}\n\
+\\subimport{parts/}{part}\n\
+\\songly{solo}\n\
+\\end{document}\n";
+
+fn write(dir: &Path, path: &str, content: &str) {
+    let path = dir.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+fn write_document(dir: &Path) {
+    write(dir, "doc/main.tex", MAIN_TEX);
+    write(dir, "doc/data.tex", "$x=0$ 50\\% \\input{table}\n");
+    write(dir, "doc/table.tex", "a table\n");
+    write(
+        dir,
+        "doc/projectile.tikz",
+        "\\begin{tikzpicture}\\end{tikzpicture}\n",
+    );
+    write(dir, "doc/chapter.tex", "a chapter\n");
+    write(
+        dir,
+        "doc/parts/part.tex",
+        "\\includegraphics[width=3cm]{figure}\n",
+    );
+    write(dir, "doc/parts/figure.png", "not really a png\n");
+}
+
+/// Tests the dependencies found by the scan: paths relative to the sandbox,
+/// `.tex` added when missing, `\subimport` changing the base directory,
+/// graphics extension lookup, comments and macro parameters ignored.
+#[test]
+fn test_pdf_scan() {
+    let sandbox = TempDir::new("yamake_latex_sandbox").unwrap();
+    write_document(sandbox.path());
+
+    let pdf = PdfFile::new("doc/main.pdf");
+    let (scan_complete, result) = pdf.scan(sandbox.path(), &[]);
+
+    assert!(scan_complete);
+    let expected: Vec<PathBuf> = [
+        "doc/data.tex",
+        "doc/table.tex",
+        "doc/projectile.tikz",
+        "doc/chapter.tex",
+        "doc/parts/part.tex",
+        "doc/parts/figure.png",
+    ]
+    .iter()
+    .map(PathBuf::from)
+    .collect();
+    assert_eq!(result, expected);
+}
+
+/// A fake engine: its `.aux` file changes on the first two runs only.
+fn fake_engine(dir: &Path) -> String {
+    let path = dir.join("fakelatex");
+    fs::write(
+        &path,
+        "#!/bin/sh\n\
+         for last; do :; done\n\
+         job=${last%.tex}\n\
+         n=$(cat runs 2>/dev/null || echo 0); n=$((n+1)); echo $n > runs\n\
+         if [ $n -gt 2 ]; then aux=2; else aux=$n; fi\n\
+         echo \"aux $aux\" > $job.aux\n\
+         cat $last data.tex > $job.pdf\n",
+    )
+    .unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    path.to_string_lossy().to_string()
+}
+
+/// Tests that the engine runs until the aux file is stable, and that a change
+/// to a scanned file rebuilds the document.
+#[test]
+fn test_pdf_build() {
+    let srcdir = TempDir::new("yamake_latex_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_latex_sandbox").unwrap();
+    let bin = TempDir::new("yamake_latex_bin").unwrap();
+    write_document(srcdir.path());
+    let engine = fake_engine(bin.path());
+
+    let mut g = G::new(srcdir.path().to_path_buf(), sandbox.path().to_path_buf());
+    let main_tex = g.add_root_node(TexFile::new("doc/main.tex")).unwrap();
+    for tex in [
+        "doc/data.tex",
+        "doc/table.tex",
+        "doc/chapter.tex",
+        "doc/parts/part.tex",
+        "doc/parts/figure.png",
+    ] {
+        g.add_root_node(TexFile::new(tex)).unwrap();
+    }
+    let tikz = g
+        .add_root_node(TikzFile::new("doc/projectile.tikz"))
+        .unwrap();
+    let pdf = g
+        .add_node(PdfFile::new("doc/main.pdf").with_engine(&engine))
+        .unwrap();
+    g.add_edge(main_tex, pdf);
+
+    assert!(g.make(), "build should succeed");
+    assert!(
+        g.g.find_edge(tikz, pdf).is_some(),
+        "tikz file should be scanned"
+    );
+    let runs = fs::read_to_string(sandbox.path().join("doc/runs")).unwrap();
+    assert_eq!(runs.trim(), "3", "aux file is stable after the third run");
+
+    // Nothing changed
+    assert!(g.make(), "second build should succeed");
+    assert_eq!(
+        g.nodes_status.get(&pdf),
+        Some(&GNodeStatus::BuildNotRequired)
+    );
+
+    // A scanned file changed
+    write(srcdir.path(), "doc/data.tex", "$x=1$\n");
+    assert!(g.make(), "third build should succeed");
+    assert_eq!(g.nodes_status.get(&pdf), Some(&GNodeStatus::BuildSuccess));
+}
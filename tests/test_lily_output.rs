@@ -0,0 +1,162 @@
+//! Test the LilyPond output directory included in a LaTeX document.
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use tempdir::TempDir;
+use yamake::latex_nodes::{LilyFile, LilyOutput, PdfFile, TexFile};
+use yamake::model::{G, GNodeStatus, RebuildReason};
+
+fn write_script(dir: &Path, name: &str, content: &str) -> String {
+    let path = dir.join(name);
+    fs::write(&path, content).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    path.to_string_lossy().to_string()
+}
+
+/// A fake lilypond: writes `<out>-systems.tex` and `<out>-1.pdf` from the score.
+const FAKE_LILYPOND: &str = "#!/bin/sh\n\
+while [ $# -gt 1 ]; do\n\
+  if [ \"$1\" = \"-o\" ]; then out=$2; shift; fi\n\
+  shift\n\
+done\n\
+name=$(basename $out)\n\
+echo \"\\\\includegraphics{$name-1}\" > $out-systems.tex\n\
+cat $1 > $out-1.pdf\n";
+
+/// A fake engine: writes the pdf from the main file and the score.
+const FAKE_LATEX: &str = "#!/bin/sh\n\
+for last; do :; done\n\
+job=${last%.tex}\n\
+cat $last solo.output/solo-1.pdf > $job.pdf\n";
+
+fn setup(
+    srcdir: &Path,
+    sandbox: &Path,
+    bin: &Path,
+) -> (G, petgraph::graph::NodeIndex, petgraph::graph::NodeIndex) {
+    let lilypond = write_script(bin, "fakelilypond", FAKE_LILYPOND);
+    let latex = write_script(bin, "fakelatex", FAKE_LATEX);
+
+    let mut g = G::new(srcdir.to_path_buf(), sandbox.to_path_buf());
+    let main_tex = g.add_root_node(TexFile::new("doc/main.tex")).unwrap();
+    let solo_ly = g.add_root_node(LilyFile::new("doc/solo.ly")).unwrap();
+    let solo = g
+        .add_node(LilyOutput::new("doc/solo.output").with_program(&lilypond))
+        .unwrap();
+    let pdf = g
+        .add_node(PdfFile::new("doc/main.pdf").with_engine(&latex))
+        .unwrap();
+    g.add_edge(solo_ly, solo);
+    g.add_edge(main_tex, pdf);
+    g.add_edge(solo, pdf);
+    (g, solo, pdf)
+}
+
+/// Tests that the score is built into its output directory, with the entry
+/// point for `\subimport`, and that the document is rebuilt when the score
+/// changes, but not otherwise.
+#[test]
+fn test_lily_output() {
+    let srcdir = TempDir::new("yamake_lily_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_lily_sandbox").unwrap();
+    let bin = TempDir::new("yamake_lily_bin").unwrap();
+
+    fs::create_dir_all(srcdir.path().join("doc")).unwrap();
+    fs::write(
+        srcdir.path().join("doc/main.tex"),
+        "\\newcommand{\\songly}[1]{\\subimport{#1.output}{#1}}\n\\songly{solo}\n",
+    )
+    .unwrap();
+    fs::write(srcdir.path().join("doc/solo.ly"), "{ c'4 d'4 }\n").unwrap();
+
+    let (mut g, _, _) = setup(srcdir.path(), sandbox.path(), bin.path());
+    assert!(g.make(), "build should succeed");
+    let output = sandbox.path().join("doc/solo.output");
+    assert_eq!(
+        fs::read_to_string(output.join("solo.tex")).unwrap(),
+        "\\input{solo-systems.tex}\n"
+    );
+    assert!(output.join("solo-systems.tex").exists());
+    assert!(output.join("solo-1.pdf").exists());
+
+    // Nothing changed: the directory digest is stable
+    let (mut g, solo, pdf) = setup(srcdir.path(), sandbox.path(), bin.path());
+    assert!(g.make(), "second build should succeed");
+    assert_eq!(
+        g.nodes_status.get(&solo),
+        Some(&GNodeStatus::BuildNotRequired)
+    );
+    assert_eq!(
+        g.nodes_status.get(&pdf),
+        Some(&GNodeStatus::BuildNotRequired)
+    );
+
+    // The score changed: the directory and the document are rebuilt
+    fs::write(srcdir.path().join("doc/solo.ly"), "{ e'4 f'4 }\n").unwrap();
+    let (mut g, solo, pdf) = setup(srcdir.path(), sandbox.path(), bin.path());
+    assert!(g.make(), "third build should succeed");
+    assert_eq!(g.nodes_status.get(&solo), Some(&GNodeStatus::BuildSuccess));
+    assert_eq!(g.nodes_status.get(&pdf), Some(&GNodeStatus::BuildSuccess));
+}
+
+/// Tests that an `\include` is resolved against the directory of the including
+/// file, and that one bundled with lilypond is not a missing dependency.
+#[test]
+fn test_lily_output_includes() {
+    let srcdir = TempDir::new("yamake_lily_inc_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_lily_inc_sandbox").unwrap();
+    let bin = TempDir::new("yamake_lily_inc_bin").unwrap();
+
+    fs::create_dir_all(srcdir.path().join("doc/parts")).unwrap();
+    fs::write(
+        srcdir.path().join("doc/main.tex"),
+        "\\subimport{solo.output}{solo}\n",
+    )
+    .unwrap();
+    fs::write(
+        srcdir.path().join("doc/solo.ly"),
+        "\\include \"english.ly\"\n\\include \"parts/melody.ly\"\n",
+    )
+    .unwrap();
+    fs::write(
+        srcdir.path().join("doc/parts/melody.ly"),
+        "\\include \"notes.ly\"\n",
+    )
+    .unwrap();
+    fs::write(srcdir.path().join("doc/parts/notes.ly"), "{ c'4 }\n").unwrap();
+
+    let setup_parts = || {
+        let (mut g, solo, pdf) = setup(srcdir.path(), sandbox.path(), bin.path());
+        g.add_root_node(LilyFile::new("doc/parts/melody.ly"))
+            .unwrap();
+        g.add_root_node(LilyFile::new("doc/parts/notes.ly"))
+            .unwrap();
+        (g, solo, pdf)
+    };
+
+    let (mut g, solo, _) = setup_parts();
+    assert!(g.make(), "build should succeed");
+    assert_eq!(g.nodes_status.get(&solo), Some(&GNodeStatus::BuildSuccess));
+    let notes = g.node_index(Path::new("doc/parts/notes.ly")).unwrap();
+    assert!(g.g.edges_connecting(notes, solo).next().is_some());
+
+    // Nothing changed: english.ly does not make the scan incomplete
+    let (mut g, solo, _) = setup_parts();
+    assert!(g.make(), "second build should succeed");
+    assert_eq!(
+        g.nodes_status.get(&solo),
+        Some(&GNodeStatus::BuildNotRequired)
+    );
+
+    // The nested include changed
+    fs::write(srcdir.path().join("doc/parts/notes.ly"), "{ d'4 }\n").unwrap();
+    let (mut g, solo, _) = setup_parts();
+    assert!(g.make(), "third build should succeed");
+    match g.rebuild_reasons.get(&solo) {
+        Some(RebuildReason::PredecessorChanged { pathbuf, .. }) => {
+            assert_eq!(pathbuf, Path::new("doc/parts/notes.ly"))
+        }
+        other => panic!("expected PredecessorChanged, got {other:?}"),
+    }
+}
@@ -0,0 +1,148 @@
+//! Test the markdown nodes: MarkdownFile and HtmlFile.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempdir::TempDir;
+use yamake::generic_nodes::TemplateFile;
+use yamake::markdown_nodes::{HtmlFile, MarkdownFile};
+use yamake::model::{G, GNode, GNodeStatus, GRootNode};
+
+const INDEX_MD: &str = "# Getting started {#getting-started}\n\n\
+See [the rules](howto/rules.md#toolchain), [the site](https://example.com), \
+[the FAQ](faq.html) and [below](#usage).\n\n\
+![the graph](img/graph.png)\n\n\
+## Usage {#use}\n\n\
+## Usage\n\n\
+```mermaid\nflowchart LR\n  a --> b\n```\n";
+
+const PAGE_J2: &str = "<html><title>{{ title }}</title><body>\n{{ content }}</body></html>\n";
+
+fn write(dir: &Path, path: &str, content: &str) {
+    let path = dir.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+/// Tests that the scan finds the local links and images, relative to the
+/// markdown file, but not the links to other pages, and is incomplete while
+/// an image is missing.
+#[test]
+fn test_html_scan() {
+    let sandbox = TempDir::new("yamake_markdown_scan").unwrap();
+    write(sandbox.path(), "doc/index.md", INDEX_MD);
+
+    let markdown = MarkdownFile::new("doc/index.md");
+    let html = HtmlFile::new("doc/index.html");
+    let (scan_complete, result) = html.scan(sandbox.path(), &[&markdown]);
+    assert!(!scan_complete, "the image is missing");
+    let expected: Vec<PathBuf> = ["doc/howto/rules.md", "doc/img/graph.png"]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+    assert_eq!(result, expected);
+
+    write(sandbox.path(), "doc/img/graph.png", "not really a png\n");
+    let (scan_complete, _) = html.scan(sandbox.path(), &[&markdown]);
+    assert!(scan_complete);
+}
+
+fn setup(srcdir: &Path, sandbox: &Path, template: bool) -> G {
+    let mut g = G::new(srcdir.to_path_buf(), sandbox.to_path_buf());
+    let markdown = g.add_root_node(MarkdownFile::new("doc/index.md")).unwrap();
+    g.add_root_node(Image {
+        name: "doc/img/graph.png".to_string(),
+    })
+    .unwrap();
+    let html = g.add_node(HtmlFile::new("doc/index.html")).unwrap();
+    g.add_edge(markdown, html);
+    if template {
+        let page = g.add_root_node(TemplateFile::new("doc/page.j2")).unwrap();
+        g.add_edge(page, html);
+    }
+    g
+}
+
+/// An image, a predecessor of the page once found by the scan.
+struct Image {
+    name: String,
+}
+
+impl GRootNode for Image {
+    fn tag(&self) -> String {
+        "Image".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
+
+/// Tests the HTML of a page: heading ids, mermaid block, template, and a
+/// rebuild when an image found by the scan changes.
+#[test]
+fn test_html_file() {
+    let srcdir = TempDir::new("yamake_markdown_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_markdown_sandbox").unwrap();
+    write(srcdir.path(), "doc/index.md", INDEX_MD);
+    write(srcdir.path(), "doc/img/graph.png", "not really a png\n");
+    write(srcdir.path(), "doc/page.j2", PAGE_J2);
+
+    let mut g = setup(srcdir.path(), sandbox.path(), false);
+    assert!(g.make(), "build should succeed");
+    let html = fs::read_to_string(sandbox.path().join("doc/index.html")).unwrap();
+    for expected in [
+        "<h1 id=\"getting-started\">Getting started</h1>",
+        "<a href=\"howto/rules.md#toolchain\">",
+        "<img src=\"img/graph.png\" alt=\"the graph\" />",
+        "<h2 id=\"use\">Usage</h2>",
+        "<h2>Usage</h2>",
+        "<pre class=\"mermaid\">flowchart LR\n  a --&gt; b\n</pre>",
+    ] {
+        assert!(html.contains(expected), "{expected} not in {html}");
+    }
+
+    // The image found by the scan changed
+    write(srcdir.path(), "doc/img/graph.png", "another png\n");
+    let mut g = setup(srcdir.path(), sandbox.path(), false);
+    assert!(g.make(), "second build should succeed");
+    let idx = g.node_index(Path::new("doc/index.html")).unwrap();
+    assert_eq!(
+        g.nodes_status.get(&idx),
+        Some(&GNodeStatus::BuildNotChanged)
+    );
+
+    // Wrapped in a template
+    let mut g = setup(srcdir.path(), sandbox.path(), true);
+    assert!(g.make(), "build with a template should succeed");
+    let html = fs::read_to_string(sandbox.path().join("doc/index.html")).unwrap();
+    assert!(
+        html.starts_with("<html><title>Getting started</title><body>\n<h1 id=\"getting-started\">"),
+        "{html}"
+    );
+    assert!(html.ends_with("</pre>\n</body></html>"), "{html}");
+}
+
+/// Tests that two pages linking each other are built: the links between
+/// pages are not dependencies, which would make a cycle.
+#[test]
+fn test_html_pages_linking_each_other() {
+    let srcdir = TempDir::new("yamake_markdown_links_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_markdown_links_sandbox").unwrap();
+    write(srcdir.path(), "doc/a.md", "# A\n\nSee [B](b.html).\n");
+    write(srcdir.path(), "doc/b.md", "# B\n\nSee [A](a.html#a).\n");
+
+    let mut g = G::new(srcdir.path().to_path_buf(), sandbox.path().to_path_buf());
+    for page in ["a", "b"] {
+        let markdown = g
+            .add_root_node(MarkdownFile::new(&format!("doc/{page}.md")))
+            .unwrap();
+        let html = g
+            .add_node(HtmlFile::new(&format!("doc/{page}.html")))
+            .unwrap();
+        g.add_edge(markdown, html);
+    }
+    assert!(g.make(), "build should succeed");
+    assert!(!petgraph::algo::is_cyclic_directed(&g.g));
+    let html = fs::read_to_string(sandbox.path().join("doc/b.html")).unwrap();
+    assert!(html.contains("<a href=\"a.html#a\">"), "{html}");
+}
@@ -0,0 +1,147 @@
+//! Test OFile scanning of angle-bracket includes and external headers.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempdir::TempDir;
+use yamake::c_nodes::{CFile, HFile, OFile};
+use yamake::model::{G, GNodeStatus, MakeOutput, OutputInfo, RebuildReason};
+
+const MAIN_C: &str = "#include <stdio.h>\n#include <vendor.h>\nint main() { return VENDOR; }\n";
+
+fn report_entry(sandbox: &Path, path: &str) -> OutputInfo {
+    let report = fs::File::open(sandbox.join("make-report.yml")).unwrap();
+    let output: MakeOutput = serde_yaml::from_reader(report).unwrap();
+    output
+        .nodes
+        .into_iter()
+        .find(|n| n.pathbuf == Path::new(path))
+        .unwrap()
+}
+
+/// Tests that a vendored header reached via `#include <...>` through an include
+/// path outside the sandbox is tracked by digest:
+/// - It is recorded as an external dependency in the report
+/// - An unchanged header does not rebuild the object file
+/// - A changed header rebuilds it, with an ExternalChanged reason
+/// - `<stdio.h>`, not in the include paths, is ignored
+#[test]
+fn test_angle_include_external_header() {
+    let srcdir = TempDir::new("yamake_angle_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_angle_sandbox").unwrap();
+    let vendor = TempDir::new("yamake_angle_vendor").unwrap();
+    let vendor_h = vendor.path().join("vendor.h");
+
+    fs::create_dir_all(srcdir.path().join("p")).unwrap();
+    fs::write(srcdir.path().join("p/main.c"), MAIN_C).unwrap();
+    fs::write(&vendor_h, "#define VENDOR 0\n").unwrap();
+
+    let mut g = G::new(srcdir.path().to_path_buf(), sandbox.path().to_path_buf());
+    let main_c = g.add_root_node(CFile::new("p/main.c")).unwrap();
+    let main_o = g
+        .add_node(
+            OFile::new("p/main.o", vec![vendor.path().to_path_buf()], vec![]).with_angle_includes(),
+        )
+        .unwrap();
+    g.add_edge(main_c, main_o);
+
+    assert!(g.make(), "First build should succeed");
+
+    let info = report_entry(sandbox.path(), "p/main.o");
+    assert_eq!(info.external_dependencies.len(), 1);
+    assert_eq!(info.external_dependencies[0].pathbuf, vendor_h);
+    assert!(info.external_dependencies[0].digest.is_some());
+    assert!(
+        !info
+            .scanned_dependencies
+            .contains(&PathBuf::from("stdio.h"))
+    );
+
+    // Nothing changed: no rebuild
+    assert!(g.make(), "Second build should succeed");
+    assert_eq!(
+        g.nodes_status.get(&main_o),
+        Some(&GNodeStatus::BuildNotRequired)
+    );
+
+    // The vendored header changed: rebuild
+    fs::write(&vendor_h, "#define VENDOR 1\n").unwrap();
+    assert!(g.make(), "Third build should succeed");
+    assert_eq!(
+        g.nodes_status.get(&main_o),
+        Some(&GNodeStatus::BuildSuccess)
+    );
+    match g.rebuild_reasons.get(&main_o) {
+        Some(RebuildReason::ExternalChanged { pathbuf, .. }) => assert_eq!(pathbuf, &vendor_h),
+        other => panic!("unexpected rebuild reason: {other:?}"),
+    }
+}
+
+/// Tests that angle-bracket includes are not followed by default.
+#[test]
+fn test_angle_include_ignored_by_default() {
+    let sandbox = TempDir::new("yamake_angle_sandbox").unwrap();
+    let vendor = TempDir::new("yamake_angle_vendor").unwrap();
+
+    fs::create_dir_all(sandbox.path().join("p")).unwrap();
+    fs::write(sandbox.path().join("p/main.c"), MAIN_C).unwrap();
+    fs::write(vendor.path().join("vendor.h"), "#define VENDOR 0\n").unwrap();
+
+    let main_c = CFile::new("p/main.c");
+    let main_o = OFile::new("p/main.o", vec![vendor.path().to_path_buf()], vec![]);
+    let (scan_complete, result) = yamake::model::GNode::scan(&main_o, sandbox.path(), &[&main_c]);
+
+    assert!(scan_complete);
+    assert!(result.is_empty());
+}
+
+/// Tests that a vendored header reached through a relative include path is
+/// found in the sandbox, as gcc finds it, and that a change rebuilds the
+/// object file.
+#[test]
+fn test_angle_include_relative_include_path() {
+    let srcdir = TempDir::new("yamake_angle_rel_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_angle_rel_sandbox").unwrap();
+    fs::create_dir_all(srcdir.path().join("p")).unwrap();
+    fs::create_dir_all(srcdir.path().join("vendor/include")).unwrap();
+    fs::write(srcdir.path().join("p/main.c"), MAIN_C).unwrap();
+    fs::write(
+        srcdir.path().join("vendor/include/vendor.h"),
+        "#define VENDOR 0\n",
+    )
+    .unwrap();
+
+    let setup = || {
+        let mut g = G::new(srcdir.path().to_path_buf(), sandbox.path().to_path_buf());
+        let main_c = g.add_root_node(CFile::new("p/main.c")).unwrap();
+        let vendor_h = g
+            .add_root_node(HFile::new("vendor/include/vendor.h"))
+            .unwrap();
+        let main_o = g
+            .add_node(
+                OFile::new("p/main.o", vec![PathBuf::from("vendor/include")], vec![])
+                    .with_angle_includes(),
+            )
+            .unwrap();
+        g.add_edge(main_c, main_o);
+        (g, vendor_h, main_o)
+    };
+
+    let (mut g, vendor_h, main_o) = setup();
+    assert!(g.make(), "First build should succeed");
+    assert!(g.g.find_edge(vendor_h, main_o).is_some());
+    let info = report_entry(sandbox.path(), "p/main.o");
+    assert!(info.external_dependencies.is_empty());
+
+    // The vendored header changed: rebuild
+    fs::write(
+        srcdir.path().join("vendor/include/vendor.h"),
+        "#define VENDOR 1\n",
+    )
+    .unwrap();
+    let (mut g, _, main_o) = setup();
+    assert!(g.make(), "Second build should succeed");
+    assert_eq!(
+        g.nodes_status.get(&main_o),
+        Some(&GNodeStatus::BuildSuccess)
+    );
+}
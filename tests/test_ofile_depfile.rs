@@ -0,0 +1,160 @@
+//! Test OFile header dependencies from gcc depfiles.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempdir::TempDir;
+use yamake::c_nodes::{CFile, HFile, OFile};
+use yamake::model::{G, GNodeStatus, MakeOutput};
+
+/// Tests that the dependencies of the depfile written by gcc replace the regex
+/// scan after the first build.
+///
+/// main.c includes `<p/angle.h>`, which the regex scan ignores:
+/// - The first scan only finds the quoted include, and gcc writes `p/main.d`
+/// - Its dependencies are stored in the report and add the angle.h edge
+/// - Changing angle.h then rebuilds main.o
+/// - A header included since the last build gets its edge once main.c, which
+///   changed, is compiled again
+#[test]
+fn test_ofile_depfile() {
+    let srcdir = TempDir::new("yamake_depfile_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_depfile_sandbox").unwrap();
+    let srcdir_path = srcdir.path().to_path_buf();
+    let sandbox_path = sandbox.path().to_path_buf();
+
+    fs::create_dir_all(srcdir_path.join("p")).unwrap();
+    fs::write(
+        srcdir_path.join("p/main.c"),
+        "#include \"p/quoted.h\"\n#include <p/angle.h>\nint main() { return QUOTED + ANGLE; }\n",
+    )
+    .unwrap();
+    fs::write(srcdir_path.join("p/quoted.h"), "#define QUOTED 1\n").unwrap();
+    fs::write(srcdir_path.join("p/angle.h"), "#define ANGLE 2\n").unwrap();
+
+    let mut g = G::new(srcdir_path.clone(), sandbox_path.clone());
+    let main_c = g.add_root_node(CFile::new("p/main.c")).unwrap();
+    let quoted_h = g.add_root_node(HFile::new("p/quoted.h")).unwrap();
+    let angle_h = g.add_root_node(HFile::new("p/angle.h")).unwrap();
+    let main_o = g
+        .add_node(OFile::new("p/main.o", vec![], vec![]).with_depfile())
+        .unwrap();
+    g.add_edge(main_c, main_o);
+
+    // First build: the regex scan bootstraps the build, then the depfile
+    // written by gcc is scanned on the next iteration of make()
+    assert!(g.make(), "First build should succeed");
+    assert!(
+        sandbox_path.join("p/main.d").exists(),
+        "depfile should exist"
+    );
+    assert!(g.g.find_edge(quoted_h, main_o).is_some());
+    assert!(
+        g.g.find_edge(angle_h, main_o).is_some(),
+        "angle.h edge should come from the depfile"
+    );
+
+    let report = fs::File::open(sandbox_path.join("make-report.yml")).unwrap();
+    let output: MakeOutput = serde_yaml::from_reader(report).unwrap();
+    let main_o_info = output
+        .nodes
+        .iter()
+        .find(|n| n.pathbuf == Path::new("p/main.o"))
+        .unwrap();
+    assert!(
+        main_o_info
+            .scanned_dependencies
+            .contains(&PathBuf::from("p/angle.h"))
+    );
+    let discovered = main_o_info.discovered_dependencies.as_ref().unwrap();
+    assert!(discovered.contains(&PathBuf::from("p/angle.h")));
+    assert!(discovered.iter().all(|p| p.is_relative()));
+
+    // Changing angle.h rebuilds main.o
+    fs::write(srcdir_path.join("p/angle.h"), "#define ANGLE 3\n").unwrap();
+    assert!(g.make(), "Second build should succeed");
+    assert_eq!(
+        g.nodes_status.get(&main_o),
+        Some(&GNodeStatus::BuildSuccess)
+    );
+
+    // A new header, included since the last build: the report is stale until
+    // main.c is compiled again
+    fs::write(srcdir_path.join("p/extra.h"), "#define EXTRA 4\n").unwrap();
+    fs::write(
+        srcdir_path.join("p/main.c"),
+        "#include \"p/quoted.h\"\n#include \"p/extra.h\"\n#include <p/angle.h>\n\
+         int main() { return QUOTED + EXTRA + ANGLE; }\n",
+    )
+    .unwrap();
+    let mut g = G::new(srcdir_path.clone(), sandbox_path.clone());
+    let main_c = g.add_root_node(CFile::new("p/main.c")).unwrap();
+    g.add_root_node(HFile::new("p/quoted.h")).unwrap();
+    let angle_h = g.add_root_node(HFile::new("p/angle.h")).unwrap();
+    let extra_h = g.add_root_node(HFile::new("p/extra.h")).unwrap();
+    let main_o = g
+        .add_node(OFile::new("p/main.o", vec![], vec![]).with_depfile())
+        .unwrap();
+    g.add_edge(main_c, main_o);
+    assert!(g.make(), "Third build should succeed");
+    assert!(g.g.find_edge(extra_h, main_o).is_some());
+    assert!(g.g.find_edge(angle_h, main_o).is_some());
+}
+
+/// Tests that an `#include` of a missing header behind an `#if` that gcc skips
+/// only makes the first scan incomplete: once the depfile exists, it replaces
+/// the regex scan and main.o is not rebuilt.
+#[test]
+fn test_ofile_depfile_guarded_include() {
+    let srcdir = TempDir::new("yamake_depfile_guard_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_depfile_guard_sandbox").unwrap();
+    let srcdir_path = srcdir.path().to_path_buf();
+    let sandbox_path = sandbox.path().to_path_buf();
+
+    fs::create_dir_all(srcdir_path.join("p")).unwrap();
+    fs::write(
+        srcdir_path.join("p/main.c"),
+        "#include \"p/main.h\"\n#ifdef WINDOWS\n#include \"p/missing.h\"\n#endif\n\
+         int main() { return MAIN; }\n",
+    )
+    .unwrap();
+    fs::write(srcdir_path.join("p/main.h"), "#define MAIN 0\n").unwrap();
+
+    let setup = || {
+        let mut g = G::new(srcdir_path.clone(), sandbox_path.clone());
+        let main_c = g.add_root_node(CFile::new("p/main.c")).unwrap();
+        g.add_root_node(HFile::new("p/main.h")).unwrap();
+        let main_o = g
+            .add_node(OFile::new("p/main.o", vec![], vec![]).with_depfile())
+            .unwrap();
+        g.add_edge(main_c, main_o);
+        (g, main_o)
+    };
+
+    // The regex scan cannot find p/missing.h: the first build bootstraps the depfile
+    let (mut g, main_o) = setup();
+    assert!(g.make(), "First build should succeed");
+    assert_eq!(
+        g.nodes_status.get(&main_o),
+        Some(&GNodeStatus::BuildSuccess)
+    );
+
+    // The depfile replaces the scan: nothing is missing, nothing to rebuild
+    let (mut g, main_o) = setup();
+    assert!(g.make(), "Second build should succeed");
+    assert_eq!(
+        g.nodes_status.get(&main_o),
+        Some(&GNodeStatus::BuildNotRequired)
+    );
+    let report = fs::File::open(sandbox_path.join("make-report.yml")).unwrap();
+    let output: MakeOutput = serde_yaml::from_reader(report).unwrap();
+    let main_o_info = output
+        .nodes
+        .iter()
+        .find(|n| n.pathbuf == Path::new("p/main.o"))
+        .unwrap();
+    assert!(
+        !main_o_info
+            .scanned_dependencies
+            .contains(&PathBuf::from("p/missing.h"))
+    );
+}
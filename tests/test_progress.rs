@@ -0,0 +1,58 @@
+//! Test the output of make() when stdout and stderr are not a terminal: no
+//! live display, only the plain status line of each iteration.
+
+use std::env;
+use std::fs;
+use std::process::{Command, Stdio};
+use tempdir::TempDir;
+use yamake::c_nodes::{CFile, OFile, XFile};
+use yamake::model::G;
+
+const CHILD_ENV: &str = "YAMAKE_TEST_PROGRESS_CHILD";
+
+/// Builds a small project. Does nothing unless run by `test_plain_output`.
+#[test]
+fn test_plain_output_child() {
+    if env::var_os(CHILD_ENV).is_none() {
+        return;
+    }
+    let srcdir = TempDir::new("yamake_progress_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_progress_sandbox").unwrap();
+    fs::create_dir_all(srcdir.path().join("p")).unwrap();
+    fs::write(srcdir.path().join("p/main.c"), "int main() { return 0; }\n").unwrap();
+
+    let mut g = G::new(srcdir.path().to_path_buf(), sandbox.path().to_path_buf());
+    let main_c = g.add_root_node(CFile::new("p/main.c")).unwrap();
+    let main_o = g.add_node(OFile::new("p/main.o", vec![], vec![])).unwrap();
+    let app = g.add_node(XFile::new("p/app")).unwrap();
+    g.add_edge(main_c, main_o);
+    g.add_edge(main_o, app);
+    assert!(g.make(), "build should succeed");
+}
+
+/// Tests that, with its output piped, make() prints the plain status lines,
+/// without colors or the escape sequences of the live display.
+#[test]
+fn test_plain_output() {
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "test_plain_output_child", "--nocapture"])
+        .env(CHILD_ENV, "1")
+        .env_remove("CLICOLOR_FORCE")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stdout.contains('\x1b'), "{stdout:?}");
+    assert!(!stderr.contains('\x1b'), "{stderr:?}");
+    assert!(!stdout.contains(" nodes ["), "{stdout}");
+
+    let status_lines: Vec<&str> = stdout.lines().filter(|l| l.starts_with("I:")).collect();
+    assert!(!status_lines.is_empty(), "{stdout}");
+    assert_eq!(
+        status_lines.last().unwrap(),
+        &"I:0 MC:1 MN:0 MF:0 SI:0 R:0 BS:2 BNC:0 BNR:0 BF:0 AF:0 C:0 TO:0"
+    );
+}
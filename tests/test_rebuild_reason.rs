@@ -0,0 +1,111 @@
+//! Test the rebuild reasons recorded by make().
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempdir::TempDir;
+use yamake::c_nodes::{AFile, CFile, HFile, OFile, XFile};
+use yamake::model::{G, MakeOutput, RebuildReason};
+
+/// Tests that make() records why each node was rebuilt.
+///
+/// - First build: every built node is rebuilt because its output is missing
+/// - After changing add.c: add.o is rebuilt because add.c changed, and nodes
+///   that were not rebuilt have no reason
+/// - The reason is also written to make-report.yml
+#[test]
+fn test_rebuild_reason() {
+    let srcdir_temp = TempDir::new("yamake_reason_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_reason_sandbox").unwrap();
+    let srcdir = srcdir_temp.path().to_path_buf();
+    let sandbox_path = sandbox.path().to_path_buf();
+
+    // Copy demo_projects/project_C to temp srcdir
+    let src_project = PathBuf::from("demo_projects/project_C");
+    let dst_project = srcdir.join("project_C");
+    fs::create_dir_all(&dst_project).unwrap();
+    for entry in fs::read_dir(&src_project).unwrap() {
+        let entry = entry.unwrap();
+        fs::copy(entry.path(), dst_project.join(entry.file_name())).unwrap();
+    }
+
+    // Use absolute path for external dependencies outside the project
+    let srcdir_abs = PathBuf::from("demo_projects").canonicalize().unwrap();
+    let include_paths = vec![srcdir_abs.join("other-deps/foo/bar")];
+
+    let mut g = G::new(srcdir.clone(), sandbox_path.clone());
+
+    let main_c = g.add_root_node(CFile::new("project_C/main.c")).unwrap();
+    let main_o = g
+        .add_node(OFile::new(
+            "project_C/main.o",
+            include_paths.clone(),
+            vec![],
+        ))
+        .unwrap();
+    let add_c = g.add_root_node(CFile::new("project_C/add.c")).unwrap();
+    let add_o = g
+        .add_node(OFile::new(
+            "project_C/add.o",
+            include_paths.clone(),
+            vec!["-DYYY_defined".to_string()],
+        ))
+        .unwrap();
+    let _add_h = g.add_root_node(HFile::new("project_C/add.h")).unwrap();
+    let _wrapper_h = g.add_root_node(HFile::new("project_C/wrapper.h")).unwrap();
+    let project_a = g.add_node(AFile::new("project_C/libproject.a")).unwrap();
+    let app = g.add_node(XFile::new("project_C/app")).unwrap();
+
+    g.add_edge(main_c, main_o);
+    g.add_edge(main_o, app);
+    g.add_edge(add_c, add_o);
+    g.add_edge(add_o, project_a);
+    g.add_edge(project_a, app);
+
+    // First build - all outputs are missing
+    assert!(g.make(), "First build should succeed");
+    assert_eq!(
+        g.explain(Path::new("project_C/add.o")),
+        Some(&RebuildReason::OutputMissing)
+    );
+    assert_eq!(
+        g.explain(Path::new("project_C/app")),
+        Some(&RebuildReason::OutputMissing)
+    );
+    assert_eq!(g.explain(Path::new("project_C/add.c")), None);
+
+    // Add a comment to add.c
+    let add_c_path = srcdir.join("project_C/add.c");
+    let content = fs::read_to_string(&add_c_path).unwrap();
+    fs::write(&add_c_path, format!("{content}\n// some comment\n")).unwrap();
+
+    assert!(g.make(), "Second build should succeed");
+
+    match g.explain(Path::new("project_C/add.o")) {
+        Some(RebuildReason::PredecessorChanged {
+            pathbuf,
+            previous_digest,
+            current_digest,
+        }) => {
+            assert_eq!(pathbuf, &PathBuf::from("project_C/add.c"));
+            assert!(previous_digest.is_some());
+            assert!(current_digest.is_some());
+            assert_ne!(previous_digest, current_digest);
+        }
+        other => panic!("add.o should be rebuilt because add.c changed, got {other:?}"),
+    }
+    assert_eq!(g.explain(Path::new("project_C/main.o")), None);
+    assert_eq!(g.explain(Path::new("project_C/app")), None);
+
+    // The reason is written to the report
+    let report = fs::File::open(sandbox_path.join("make-report.yml")).unwrap();
+    let output: MakeOutput = serde_yaml::from_reader(report).unwrap();
+    let add_o_info = output
+        .nodes
+        .iter()
+        .find(|n| n.pathbuf == Path::new("project_C/add.o"))
+        .unwrap();
+    assert!(matches!(
+        add_o_info.rebuild_reason,
+        Some(RebuildReason::PredecessorChanged { .. })
+    ));
+}
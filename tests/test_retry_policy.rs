@@ -0,0 +1,117 @@
+//! Test the retry policy of flaky build steps.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+use tempdir::TempDir;
+use yamake::c_nodes::CFile;
+use yamake::command::run_command;
+use yamake::model::{G, GNode, GNodeStatus, MakeOutput, RetryPolicy};
+
+/// A node that fails with `exit_code` until its script has run `succeed_at` times.
+struct FlakyNode {
+    name: String,
+    succeed_at: u32,
+    exit_code: i32,
+}
+
+impl GNode for FlakyNode {
+    fn build(&self, sandbox: &Path, _predecessors: &[&(dyn GNode + Send + Sync)]) -> bool {
+        let script = format!(
+            "n=$(cat \"$COUNTER\" 2>/dev/null || echo 0); n=$((n+1)); echo $n > \"$COUNTER\"; \
+             echo attempt $n >&2; [ $n -ge {} ] || exit {}; touch \"$OUT\"",
+            self.succeed_at, self.exit_code
+        );
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(script);
+        cmd.env("COUNTER", sandbox.join("counter"));
+        cmd.env("OUT", sandbox.join(&self.name));
+        run_command(&mut cmd, sandbox, &self.name)
+    }
+
+    fn retry_policy(&self) -> Option<RetryPolicy> {
+        Some(RetryPolicy::new(3, Duration::from_millis(10), vec![1]))
+    }
+
+    fn tag(&self) -> String {
+        "FlakyNode".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
+
+fn setup(srcdir: &Path, sandbox: &Path, node: FlakyNode) -> G {
+    fs::create_dir_all(srcdir.join("p")).unwrap();
+    fs::write(srcdir.join("p/main.c"), "int main() { return 0; }\n").unwrap();
+
+    let mut g = G::new(srcdir.to_path_buf(), sandbox.to_path_buf());
+    let main_c = g.add_root_node(CFile::new("p/main.c")).unwrap();
+    let flaky = g.add_node(node).unwrap();
+    g.add_edge(main_c, flaky);
+    g
+}
+
+fn report_entry(sandbox: &Path, path: &str) -> yamake::model::OutputInfo {
+    let report = fs::File::open(sandbox.join("make-report.yml")).unwrap();
+    let output: MakeOutput = serde_yaml::from_reader(report).unwrap();
+    output
+        .nodes
+        .into_iter()
+        .find(|n| n.pathbuf == Path::new(path))
+        .unwrap()
+}
+
+/// Tests that a node failing twice with a retried exit code succeeds on the third
+/// attempt, keeps the logs of each attempt, and is flagged as flaky in the report.
+#[test]
+fn test_retry_until_success() {
+    let srcdir = TempDir::new("yamake_retry_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_retry_sandbox").unwrap();
+
+    let node = FlakyNode {
+        name: "p/flaky".to_string(),
+        succeed_at: 3,
+        exit_code: 1,
+    };
+    let mut g = setup(srcdir.path(), sandbox.path(), node);
+
+    assert!(g.make(), "make should succeed after retries");
+
+    let info = report_entry(sandbox.path(), "p/flaky");
+    assert_eq!(info.status, GNodeStatus::BuildSuccess);
+    assert_eq!(info.attempts, 3);
+    assert!(info.flaky, "node should be flagged as flaky");
+
+    for attempt in 1..=3 {
+        let stderr = sandbox
+            .path()
+            .join(format!("logs/p/flaky.stderr.{attempt}"));
+        let content = fs::read_to_string(&stderr).unwrap();
+        assert!(content.contains(&format!("attempt {attempt}")));
+    }
+}
+
+/// Tests that a failure with an exit code that is not in the policy is not retried.
+#[test]
+fn test_no_retry_on_other_exit_code() {
+    let srcdir = TempDir::new("yamake_retry_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_retry_sandbox").unwrap();
+
+    let node = FlakyNode {
+        name: "p/flaky".to_string(),
+        succeed_at: 3,
+        exit_code: 2,
+    };
+    let mut g = setup(srcdir.path(), sandbox.path(), node);
+
+    assert!(!g.make(), "make should fail");
+
+    let info = report_entry(sandbox.path(), "p/flaky");
+    assert_eq!(info.status, GNodeStatus::BuildFailed);
+    assert_eq!(info.attempts, 1);
+    assert!(!info.flaky);
+    assert!(!sandbox.path().join("logs/p/flaky.stderr.2").exists());
+}
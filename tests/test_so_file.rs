@@ -0,0 +1,116 @@
+//! Test shared libraries built with SoFile and linked by XFile.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempdir::TempDir;
+use yamake::c_nodes::{CFile, OFile, SoFile, XFile};
+use yamake::model::{G, GRootNode};
+
+/// A linker version script, as a root node.
+struct MapFile {
+    name: String,
+}
+
+impl GRootNode for MapFile {
+    fn tag(&self) -> String {
+        "MapFile".to_string()
+    }
+
+    fn pathbuf(&self) -> std::path::PathBuf {
+        std::path::PathBuf::from(&self.name)
+    }
+}
+
+fn tool_output(program: &str, args: &[&str], file: &Path) -> String {
+    let output = Command::new(program).args(args).arg(file).output().unwrap();
+    assert!(output.status.success(), "{program} should succeed");
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+/// Tests a plugin library linked into an application:
+/// - its objects are compiled with `-fPIC`, without `with_pic`
+/// - it has the soname, and only exports the symbols of its version script
+/// - the application finds it at run time through an `$ORIGIN` rpath
+#[test]
+fn test_so_file() {
+    let srcdir = TempDir::new("yamake_so_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_so_sandbox").unwrap();
+    let srcdir_path = srcdir.path();
+    let sandbox_path = sandbox.path();
+
+    fs::create_dir_all(srcdir_path.join("p/plugin")).unwrap();
+    fs::create_dir_all(srcdir_path.join("p/bin")).unwrap();
+    fs::write(
+        srcdir_path.join("p/plugin/plugin.c"),
+        "int plugin_internal(int a) { return a * 2; }\n\
+         int plugin_value(void) { return plugin_internal(21); }\n",
+    )
+    .unwrap();
+    fs::write(
+        srcdir_path.join("p/plugin/plugin.map"),
+        "{ global: plugin_value; local: *; };\n",
+    )
+    .unwrap();
+    fs::write(
+        srcdir_path.join("p/bin/main.c"),
+        "int plugin_value(void);\nint main(void) { return plugin_value() - 42; }\n",
+    )
+    .unwrap();
+
+    let mut g = G::new(srcdir_path.to_path_buf(), sandbox_path.to_path_buf());
+    let plugin_c = g.add_root_node(CFile::new("p/plugin/plugin.c")).unwrap();
+    let plugin_map = g
+        .add_root_node(MapFile {
+            name: "p/plugin/plugin.map".to_string(),
+        })
+        .unwrap();
+    let main_c = g.add_root_node(CFile::new("p/bin/main.c")).unwrap();
+    let plugin_o = g
+        .add_node(OFile::new("p/plugin/plugin.o", vec![], vec![]))
+        .unwrap();
+    let main_o = g
+        .add_node(OFile::new("p/bin/main.o", vec![], vec![]))
+        .unwrap();
+    let plugin_so = g
+        .add_node(
+            SoFile::new("p/plugin/libplugin.so")
+                .with_soname("libplugin.so")
+                .with_version_script("p/plugin/plugin.map"),
+        )
+        .unwrap();
+    let app = g.add_node(XFile::new("p/bin/app")).unwrap();
+    g.add_edge(plugin_c, plugin_o);
+    g.add_edge(plugin_o, plugin_so);
+    g.add_edge(plugin_map, plugin_so);
+    g.add_edge(main_c, main_o);
+    g.add_edge(main_o, app);
+    g.add_edge(plugin_so, app);
+
+    assert!(g.make(), "build should succeed");
+
+    let plugin_log =
+        fs::read_to_string(sandbox_path.join("logs/p/plugin/plugin.o.stdout")).unwrap();
+    assert!(plugin_log.lines().next().unwrap().contains("-fPIC"));
+    let main_log = fs::read_to_string(sandbox_path.join("logs/p/bin/main.o.stdout")).unwrap();
+    assert!(!main_log.lines().next().unwrap().contains("-fPIC"));
+
+    let so = sandbox_path.join("p/plugin/libplugin.so");
+    let dynamic = tool_output("readelf", &["-d"], &so);
+    assert!(dynamic.contains("Library soname: [libplugin.so]"));
+    let symbols = tool_output("nm", &["-D", "--defined-only"], &so);
+    assert!(symbols.contains("plugin_value"));
+    assert!(!symbols.contains("plugin_internal"));
+
+    let app_path = sandbox_path.join("p/bin/app");
+    let dynamic = tool_output("readelf", &["-d"], &app_path);
+    assert!(dynamic.contains("$ORIGIN/../plugin"));
+
+    // Run from another directory, without LD_LIBRARY_PATH
+    let status = Command::new(&app_path)
+        .current_dir("/")
+        .env_remove("LD_LIBRARY_PATH")
+        .status()
+        .unwrap();
+    assert!(status.success(), "app should load the plugin and return 0");
+}
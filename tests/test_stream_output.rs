@@ -0,0 +1,31 @@
+//! Test live streaming of command output.
+
+mod common;
+
+use common::{ShellNode, setup_main_c};
+use std::fs;
+use tempdir::TempDir;
+
+/// Tests that streaming the output to the terminal still writes the complete
+/// output, including a last line without newline, to the log files.
+#[test]
+fn test_stream_output_keeps_logs() {
+    let srcdir = TempDir::new("yamake_stream_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_stream_sandbox").unwrap();
+    let (mut g, main_c) = setup_main_c(srcdir.path(), sandbox.path());
+    g.stream_output = true;
+    let chatty = g
+        .add_node(ShellNode::new(
+            "p/chatty",
+            "echo line1; echo err1 >&2; echo line2; printf tail; touch \"$OUT\"",
+        ))
+        .unwrap();
+    g.add_edge(main_c, chatty);
+
+    assert!(g.make(), "make should succeed");
+
+    let stdout = fs::read_to_string(sandbox.path().join("logs/p/chatty.stdout")).unwrap();
+    assert!(stdout.ends_with("line1\nline2\ntail"), "got {stdout:?}");
+    let stderr = fs::read_to_string(sandbox.path().join("logs/p/chatty.stderr")).unwrap();
+    assert!(stderr.ends_with("err1\n"), "got {stderr:?}");
+}
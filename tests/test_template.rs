@@ -0,0 +1,249 @@
+//! Test the template nodes: TemplateNode and TemplateList.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempdir::TempDir;
+use yamake::c_nodes::{AFile, CFile, HFile, OFile, XFile};
+use yamake::generic_nodes::{DataFile, TemplateFile, TemplateList, TemplateNode};
+use yamake::model::{G, GNodeStatus, RebuildReason};
+
+const GREETINGS_YML: &str = "languages:\n  FR:\n    hello: Bonjour\n  EN:\n    hello: Hello\n";
+
+const GREETINGS_H_J2: &str = "{% for code, l in languages|items %}\
+#define HELLO_{{ code }} \"{{ l.hello }}\"\n{% endfor %}";
+
+fn setup_greetings(srcdir: &Path, sandbox: &Path, data: &str) -> G {
+    let mut g = G::new(srcdir.to_path_buf(), sandbox.to_path_buf());
+    let template = g
+        .add_root_node(TemplateFile::new("p/greetings.h.j2"))
+        .unwrap();
+    let data = g.add_root_node(DataFile::new(data)).unwrap();
+    let header = g
+        .add_node(TemplateNode::new("p/greetings.h", "HFile"))
+        .unwrap();
+    g.add_edge(template, header);
+    g.add_edge(data, header);
+    g
+}
+
+/// Tests that a template is rendered with YAML or JSON data, is rendered again
+/// when the data changes, and fails on an undefined variable.
+#[test]
+fn test_template_node() {
+    let srcdir = TempDir::new("yamake_template_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_template_sandbox").unwrap();
+    fs::create_dir_all(srcdir.path().join("p")).unwrap();
+    fs::write(srcdir.path().join("p/greetings.h.j2"), GREETINGS_H_J2).unwrap();
+    fs::write(srcdir.path().join("p/greetings.yml"), GREETINGS_YML).unwrap();
+
+    let mut g = setup_greetings(srcdir.path(), sandbox.path(), "p/greetings.yml");
+    assert!(g.make(), "build should succeed");
+    let header = sandbox.path().join("p/greetings.h");
+    assert_eq!(
+        fs::read_to_string(&header).unwrap(),
+        "#define HELLO_EN \"Hello\"\n#define HELLO_FR \"Bonjour\"\n"
+    );
+
+    // Nothing changed
+    let mut g = setup_greetings(srcdir.path(), sandbox.path(), "p/greetings.yml");
+    assert!(g.make(), "second build should succeed");
+    let idx = g.node_index(Path::new("p/greetings.h")).unwrap();
+    assert_eq!(
+        g.nodes_status.get(&idx),
+        Some(&GNodeStatus::BuildNotRequired)
+    );
+
+    // JSON data
+    fs::write(
+        srcdir.path().join("p/greetings.json"),
+        r#"{"languages": {"DE": {"hello": "Hallo"}}}"#,
+    )
+    .unwrap();
+    let mut g = setup_greetings(srcdir.path(), sandbox.path(), "p/greetings.json");
+    assert!(g.make(), "JSON build should succeed");
+    assert_eq!(
+        fs::read_to_string(&header).unwrap(),
+        "#define HELLO_DE \"Hallo\"\n"
+    );
+
+    // An undefined variable
+    fs::write(
+        srcdir.path().join("p/greetings.json"),
+        r#"{"languages": {"DE": {"bonjour": "Hallo"}}}"#,
+    )
+    .unwrap();
+    let mut g = setup_greetings(srcdir.path(), sandbox.path(), "p/greetings.json");
+    assert!(!g.make(), "an undefined variable should fail");
+    let idx = g.node_index(Path::new("p/greetings.h")).unwrap();
+    assert_eq!(g.nodes_status.get(&idx), Some(&GNodeStatus::BuildFailed));
+    let stderr = fs::read_to_string(sandbox.path().join("logs/p/greetings.h.stderr")).unwrap();
+    assert!(stderr.contains("undefined"), "{stderr}");
+}
+
+const LANGUAGES_YML: &str = "- language: English\n  helloworld: Hello, World!\n\
+- language: French\n  helloworld: Bonjour, le Monde!\n";
+
+const LANGUAGE_C_J2: &str =
+    "const char *get_{{ item.language }}(void) { return \"{{ item.helloworld }}\"; }\n";
+
+const MAIN_C: &str = "#include <stdio.h>\n\
+const char *get_English(void);\nconst char *get_French(void);\n\
+int main(void) { printf(\"%s\\n%s\\n\", get_English(), get_French()); return 0; }\n";
+
+fn setup_languages(srcdir: &Path, sandbox: &Path) -> G {
+    let mut g = G::new(srcdir.to_path_buf(), sandbox.to_path_buf());
+    let template = g
+        .add_root_node(TemplateFile::new("p/language.c.j2"))
+        .unwrap();
+    let data = g.add_root_node(DataFile::new("p/languages.yml")).unwrap();
+    let main_c = g.add_root_node(CFile::new("p/main.c")).unwrap();
+    let list = g
+        .add_node(
+            TemplateList::new(
+                "p/generated/languages.list",
+                "p/generated/{{ item.language }}.c",
+            )
+            .with_archive("p/generated/liblangs.a"),
+        )
+        .unwrap();
+    let main_o = g.add_node(OFile::new("p/main.o", vec![], vec![])).unwrap();
+    let lib = g.add_node(AFile::new("p/generated/liblangs.a")).unwrap();
+    let app = g.add_node(XFile::new("p/app")).unwrap();
+    g.add_edge(template, list);
+    g.add_edge(data, list);
+    g.add_edge(list, lib);
+    g.add_edge(main_c, main_o);
+    g.add_edge(main_o, app);
+    g.add_edge(lib, app);
+    g
+}
+
+/// Tests that a list renders one source per entry, compiled and archived by
+/// the nodes added by its expand, and that only the changed entry is rebuilt.
+#[test]
+fn test_template_list() {
+    let srcdir = TempDir::new("yamake_template_list_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_template_list_sandbox").unwrap();
+    fs::create_dir_all(srcdir.path().join("p/generated")).unwrap();
+    fs::write(srcdir.path().join("p/language.c.j2"), LANGUAGE_C_J2).unwrap();
+    fs::write(srcdir.path().join("p/languages.yml"), LANGUAGES_YML).unwrap();
+    fs::write(srcdir.path().join("p/main.c"), MAIN_C).unwrap();
+
+    let mut g = setup_languages(srcdir.path(), sandbox.path());
+    assert!(g.make(), "build should succeed");
+    assert_eq!(
+        fs::read_to_string(sandbox.path().join("p/generated/languages.list")).unwrap(),
+        "p/generated/English.c\np/generated/French.c\n"
+    );
+    let output = Command::new(sandbox.path().join("p/app")).output().unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Hello, World!\nBonjour, le Monde!\n"
+    );
+
+    // One entry changed: only its object is compiled again
+    fs::write(
+        srcdir.path().join("p/languages.yml"),
+        LANGUAGES_YML.replace("Bonjour", "Salut"),
+    )
+    .unwrap();
+    let mut g = setup_languages(srcdir.path(), sandbox.path());
+    assert!(g.make(), "second build should succeed");
+    let status = |path: &str| {
+        let idx = g.node_index(Path::new(path)).unwrap();
+        g.nodes_status.get(&idx).copied()
+    };
+    assert_eq!(
+        status("p/generated/English.o"),
+        Some(GNodeStatus::BuildNotRequired)
+    );
+    assert_eq!(
+        status("p/generated/French.o"),
+        Some(GNodeStatus::BuildSuccess)
+    );
+    let output = Command::new(sandbox.path().join("p/app")).output().unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Hello, World!\nSalut, le Monde!\n"
+    );
+}
+
+const LANGUAGE_INCLUDE_C_J2: &str = "#include \"greeting.h\"\n\
+const char *get_{{ item.language }}(void) { return PREFIX \"{{ item.helloworld }}\" SUFFIX; }\n";
+
+fn setup_languages_settings(srcdir: &Path, sandbox: &Path, suffix: &str) -> G {
+    let mut g = G::new(srcdir.to_path_buf(), sandbox.to_path_buf());
+    let template = g
+        .add_root_node(TemplateFile::new("p/language.c.j2"))
+        .unwrap();
+    let data = g.add_root_node(DataFile::new("p/languages.yml")).unwrap();
+    g.add_root_node(HFile::new("p/include/greeting.h")).unwrap();
+    let main_c = g.add_root_node(CFile::new("p/main.c")).unwrap();
+    let list = g
+        .add_node(
+            TemplateList::new(
+                "p/generated/languages.list",
+                "p/generated/{{ item.language }}.c",
+            )
+            .with_archive("p/generated/liblangs.a")
+            .with_include_paths(vec![PathBuf::from("p/include")])
+            .with_compile_flags(vec![format!("-DSUFFIX=\"{suffix}\"")]),
+        )
+        .unwrap();
+    let main_o = g.add_node(OFile::new("p/main.o", vec![], vec![])).unwrap();
+    let lib = g.add_node(AFile::new("p/generated/liblangs.a")).unwrap();
+    let app = g.add_node(XFile::new("p/app")).unwrap();
+    g.add_edge(template, list);
+    g.add_edge(data, list);
+    g.add_edge(list, lib);
+    g.add_edge(main_c, main_o);
+    g.add_edge(main_o, app);
+    g.add_edge(lib, app);
+    g
+}
+
+/// Tests that the generated sources are compiled with the include paths and
+/// flags of the list, and that changing them rebuilds the list and objects.
+#[test]
+fn test_template_list_settings() {
+    let srcdir = TempDir::new("yamake_template_settings_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_template_settings_sandbox").unwrap();
+    fs::create_dir_all(srcdir.path().join("p/include")).unwrap();
+    fs::write(srcdir.path().join("p/language.c.j2"), LANGUAGE_INCLUDE_C_J2).unwrap();
+    fs::write(srcdir.path().join("p/languages.yml"), LANGUAGES_YML).unwrap();
+    fs::write(
+        srcdir.path().join("p/include/greeting.h"),
+        "#define PREFIX \"> \"\n",
+    )
+    .unwrap();
+    fs::write(srcdir.path().join("p/main.c"), MAIN_C).unwrap();
+
+    let mut g = setup_languages_settings(srcdir.path(), sandbox.path(), "!");
+    assert!(g.make(), "build should succeed");
+    let output = Command::new(sandbox.path().join("p/app")).output().unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "> Hello, World!!\n> Bonjour, le Monde!!\n"
+    );
+
+    // Other flags: the list and the objects are rebuilt
+    let mut g = setup_languages_settings(srcdir.path(), sandbox.path(), "?");
+    assert!(g.make(), "second build should succeed");
+    for path in ["p/generated/languages.list", "p/generated/English.o"] {
+        let idx = g.node_index(Path::new(path)).unwrap();
+        assert!(
+            matches!(
+                g.rebuild_reasons.get(&idx),
+                Some(RebuildReason::FingerprintChanged { .. })
+            ),
+            "{path}: {:?}",
+            g.rebuild_reasons.get(&idx)
+        );
+    }
+    let output = Command::new(sandbox.path().join("p/app")).output().unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "> Hello, World!?\n> Bonjour, le Monde!?\n"
+    );
+}
@@ -0,0 +1,188 @@
+//! Test the TestRun node, which runs a built executable and checks its result.
+
+use petgraph::graph::NodeIndex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempdir::TempDir;
+use yamake::c_nodes::{CFile, OFile, TestRun, XFile};
+use yamake::model::{G, GNodeStatus, GRootNode, MakeOutput, TestResult};
+
+/// A golden file, as a root node.
+struct GoldenFile {
+    name: String,
+}
+
+impl GRootNode for GoldenFile {
+    fn tag(&self) -> String {
+        "GoldenFile".to_string()
+    }
+
+    fn pathbuf(&self) -> PathBuf {
+        PathBuf::from(&self.name)
+    }
+}
+
+/// An app that upper-cases its stdin, and exits with its first argument.
+const MAIN_C: &str = "#include <stdio.h>\n#include <stdlib.h>\n#include <ctype.h>\n\
+int main(int argc, char **argv) {\n\
+  int c;\n\
+  while ((c = getchar()) != EOF) putchar(toupper(c));\n\
+  return argc > 1 ? atoi(argv[1]) : 0;\n\
+}\n";
+
+struct Tests {
+    echo: NodeIndex,
+    exit_code: NodeIndex,
+}
+
+fn setup(srcdir: &Path, sandbox: &Path) -> (G, Tests) {
+    fs::create_dir_all(srcdir.join("p")).unwrap();
+    fs::write(srcdir.join("p/main.c"), MAIN_C).unwrap();
+    if !srcdir.join("p/echo.golden").exists() {
+        fs::write(srcdir.join("p/echo.golden"), "HELLO\nWORLD\n").unwrap();
+    }
+
+    let mut g = G::new(srcdir.to_path_buf(), sandbox.to_path_buf());
+    let main_c = g.add_root_node(CFile::new("p/main.c")).unwrap();
+    let golden = g
+        .add_root_node(GoldenFile {
+            name: "p/echo.golden".to_string(),
+        })
+        .unwrap();
+    let main_o = g.add_node(OFile::new("p/main.o", vec![], vec![])).unwrap();
+    let app = g.add_node(XFile::new("p/app")).unwrap();
+    let echo = g
+        .add_node(
+            TestRun::new("p/echo.test")
+                .with_stdin("hello\nworld\n")
+                .with_golden("p/echo.golden"),
+        )
+        .unwrap();
+    let exit_code = g
+        .add_node(
+            TestRun::new("p/exit_code.test")
+                .with_args(vec!["3".to_string()])
+                .with_expected_exit_code(3),
+        )
+        .unwrap();
+    g.add_edge(main_c, main_o);
+    g.add_edge(main_o, app);
+    g.add_edge(app, echo);
+    g.add_edge(golden, echo);
+    g.add_edge(app, exit_code);
+    (g, Tests { echo, exit_code })
+}
+
+fn test_result(sandbox: &Path, path: &str) -> Option<TestResult> {
+    let report = fs::File::open(sandbox.join("make-report.yml")).unwrap();
+    let output: MakeOutput = serde_yaml::from_reader(report).unwrap();
+    output
+        .nodes
+        .into_iter()
+        .find(|n| n.pathbuf == Path::new(path))
+        .unwrap()
+        .test_result
+}
+
+/// Tests that:
+/// - tests pass, write their result file and are reported as passed
+/// - they do not run again when nothing changed
+/// - a changed golden file runs the test again, which fails and is reported as failed
+/// - the output is compared byte for byte: a missing final newline fails
+#[test]
+fn test_test_run() {
+    let srcdir = TempDir::new("yamake_testrun_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_testrun_sandbox").unwrap();
+
+    let (mut g, tests) = setup(srcdir.path(), sandbox.path());
+    assert!(g.make(), "tests should pass");
+    assert_eq!(
+        g.nodes_status.get(&tests.echo),
+        Some(&GNodeStatus::BuildSuccess)
+    );
+    assert!(sandbox.path().join("p/echo.test").exists());
+    // The standard input is kept with the logs, not as a file of the sandbox
+    assert!(sandbox.path().join("logs/p/echo.test.stdin").exists());
+    assert!(!sandbox.path().join("p/echo.test.stdin").exists());
+    assert!(sandbox.path().join("p/exit_code.test").exists());
+    assert_eq!(
+        test_result(sandbox.path(), "p/echo.test"),
+        Some(TestResult::Passed)
+    );
+    assert_eq!(
+        test_result(sandbox.path(), "p/exit_code.test"),
+        Some(TestResult::Passed)
+    );
+
+    // Nothing changed: the tests do not run again
+    let (mut g, tests2) = setup(srcdir.path(), sandbox.path());
+    assert!(g.make(), "tests should pass");
+    assert_eq!(
+        g.nodes_status.get(&tests2.echo),
+        Some(&GNodeStatus::BuildNotRequired)
+    );
+    assert_eq!(
+        g.nodes_status.get(&tests2.exit_code),
+        Some(&GNodeStatus::BuildNotRequired)
+    );
+
+    // A changed golden file runs the echo test again, which fails
+    fs::write(srcdir.path().join("p/echo.golden"), "HELLO\nTHERE\n").unwrap();
+    let (mut g, tests3) = setup(srcdir.path(), sandbox.path());
+    assert!(!g.make(), "the echo test should fail");
+    assert_eq!(
+        g.nodes_status.get(&tests3.echo),
+        Some(&GNodeStatus::BuildFailed)
+    );
+    assert_eq!(
+        g.nodes_status.get(&tests3.exit_code),
+        Some(&GNodeStatus::BuildNotRequired)
+    );
+    assert!(!sandbox.path().join("p/echo.test").exists());
+    assert_eq!(
+        test_result(sandbox.path(), "p/echo.test"),
+        Some(TestResult::Failed)
+    );
+    let stderr = fs::read_to_string(sandbox.path().join("logs/p/echo.test.stderr")).unwrap();
+    assert!(stderr.contains("stdout differs from p/echo.golden at line 2"));
+
+    // The same lines without the final newline
+    fs::write(srcdir.path().join("p/echo.golden"), "HELLO\nWORLD").unwrap();
+    let (mut g, tests4) = setup(srcdir.path(), sandbox.path());
+    assert!(!g.make(), "the echo test should fail");
+    assert_eq!(
+        g.nodes_status.get(&tests4.echo),
+        Some(&GNodeStatus::BuildFailed)
+    );
+    let stderr = fs::read_to_string(sandbox.path().join("logs/p/echo.test.stderr")).unwrap();
+    assert!(
+        stderr.contains(r#"at line 2: "WORLD\n" instead of "WORLD""#),
+        "{stderr}"
+    );
+}
+
+/// Tests that the executable runs in the sandbox: it opens `p/main.c`, a path
+/// relative to the sandbox.
+#[test]
+fn test_test_run_in_sandbox() {
+    let srcdir = TempDir::new("yamake_testrun_cwd_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_testrun_cwd_sandbox").unwrap();
+    fs::create_dir_all(srcdir.path().join("p")).unwrap();
+    fs::write(
+        srcdir.path().join("p/main.c"),
+        "#include <stdio.h>\nint main(void) { return fopen(\"p/main.c\", \"r\") ? 0 : 1; }\n",
+    )
+    .unwrap();
+
+    let mut g = G::new(srcdir.path().to_path_buf(), sandbox.path().to_path_buf());
+    let main_c = g.add_root_node(CFile::new("p/main.c")).unwrap();
+    let main_o = g.add_node(OFile::new("p/main.o", vec![], vec![])).unwrap();
+    let app = g.add_node(XFile::new("p/app")).unwrap();
+    let test = g.add_node(TestRun::new("p/cwd.test")).unwrap();
+    g.add_edge(main_c, main_o);
+    g.add_edge(main_o, app);
+    g.add_edge(app, test);
+
+    assert!(g.make(), "the test should find p/main.c");
+    assert_eq!(g.nodes_status.get(&test), Some(&GNodeStatus::BuildSuccess));
+}
@@ -0,0 +1,179 @@
+//! Test the toolchain used by the C nodes.
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use tempdir::TempDir;
+use yamake::c_nodes::{AFile, CFile, OFile, Toolchain, XFile};
+use yamake::model::{G, RebuildReason};
+
+/// Writes a wrapper script that logs its name, `$TOOLCHAIN_MARK` and its
+/// arguments to `log`, then runs the real tool.
+fn wrapper(dir: &Path, name: &str, tool: &str, log: &Path) -> String {
+    let path = dir.join(name);
+    fs::write(
+        &path,
+        format!(
+            "#!/bin/sh\necho \"{name} $TOOLCHAIN_MARK $*\" >> {}\nexec {tool} \"$@\"\n",
+            log.display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    path.to_string_lossy().to_string()
+}
+
+/// Tests that OFile, AFile and XFile run the programs of their toolchain, with
+/// its default flags and environment.
+#[test]
+fn test_custom_toolchain() {
+    let srcdir = TempDir::new("yamake_toolchain_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_toolchain_sandbox").unwrap();
+    let bin = TempDir::new("yamake_toolchain_bin").unwrap();
+    let log = bin.path().join("log");
+
+    fs::create_dir_all(srcdir.path().join("p")).unwrap();
+    fs::write(
+        srcdir.path().join("p/main.c"),
+        "int add(int, int);\nint main() { return add(FORTY, 2) - 42; }\n",
+    )
+    .unwrap();
+    fs::write(
+        srcdir.path().join("p/add.c"),
+        "int add(int a, int b) { return a + b; }\n",
+    )
+    .unwrap();
+
+    let toolchain = Toolchain {
+        cc: wrapper(bin.path(), "my-cc", "gcc", &log),
+        ar: wrapper(bin.path(), "my-ar", "ar", &log),
+        ld: wrapper(bin.path(), "my-ld", "gcc", &log),
+        cflags: vec!["-DFORTY=40".to_string()],
+        ldflags: vec!["-Wl,--as-needed".to_string()],
+        env: vec![("TOOLCHAIN_MARK".to_string(), "marked".to_string())],
+        ..Toolchain::gnu()
+    };
+
+    let mut g = G::new(srcdir.path().to_path_buf(), sandbox.path().to_path_buf());
+    let main_c = g.add_root_node(CFile::new("p/main.c")).unwrap();
+    let add_c = g.add_root_node(CFile::new("p/add.c")).unwrap();
+    let main_o = g
+        .add_node(OFile::new("p/main.o", vec![], vec![]).with_toolchain(toolchain.clone()))
+        .unwrap();
+    let add_o = g
+        .add_node(OFile::new("p/add.o", vec![], vec![]).with_toolchain(toolchain.clone()))
+        .unwrap();
+    let lib = g
+        .add_node(AFile::new("p/libadd.a").with_toolchain(toolchain.clone()))
+        .unwrap();
+    let app = g
+        .add_node(XFile::new("p/app").with_toolchain(toolchain))
+        .unwrap();
+    g.add_edge(main_c, main_o);
+    g.add_edge(add_c, add_o);
+    g.add_edge(add_o, lib);
+    g.add_edge(main_o, app);
+    g.add_edge(lib, app);
+
+    assert!(g.make(), "build should succeed");
+
+    let log = fs::read_to_string(&log).unwrap();
+    let lines: Vec<&str> = log.lines().collect();
+    assert_eq!(
+        lines
+            .iter()
+            .filter(|l| l.starts_with("my-cc marked -c -DFORTY=40"))
+            .count(),
+        2
+    );
+    assert_eq!(
+        lines
+            .iter()
+            .filter(|l| l.starts_with("my-ar marked rcs"))
+            .count(),
+        1
+    );
+    assert_eq!(
+        lines
+            .iter()
+            .filter(|l| l.starts_with("my-ld marked -Wl,--as-needed -o"))
+            .count(),
+        1
+    );
+
+    let status = std::process::Command::new(sandbox.path().join("p/app"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+/// Tests the names of the programs of a cross toolchain.
+#[test]
+fn test_cross_toolchain() {
+    let toolchain = Toolchain::cross("aarch64-linux-gnu-");
+    assert_eq!(toolchain.cc, "aarch64-linux-gnu-gcc");
+    assert_eq!(toolchain.cxx, "aarch64-linux-gnu-g++");
+    assert_eq!(toolchain.ar, "aarch64-linux-gnu-ar");
+    assert_eq!(toolchain.ld, "aarch64-linux-gnu-gcc");
+}
+
+/// Tests that a static library is archived again when the archiver changes,
+/// and that an object is compiled again when the environment changes.
+#[test]
+fn test_toolchain_fingerprint() {
+    let srcdir = TempDir::new("yamake_toolchain_fp_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_toolchain_fp_sandbox").unwrap();
+    let bin = TempDir::new("yamake_toolchain_fp_bin").unwrap();
+    let log = bin.path().join("log");
+
+    fs::create_dir_all(srcdir.path().join("p")).unwrap();
+    fs::write(
+        srcdir.path().join("p/add.c"),
+        "int add(int a, int b) { return a + b; }\n",
+    )
+    .unwrap();
+
+    let build = |toolchain: Toolchain| {
+        let mut g = G::new(srcdir.path().to_path_buf(), sandbox.path().to_path_buf());
+        let add_c = g.add_root_node(CFile::new("p/add.c")).unwrap();
+        let add_o = g
+            .add_node(OFile::new("p/add.o", vec![], vec![]).with_toolchain(toolchain.clone()))
+            .unwrap();
+        let lib = g
+            .add_node(AFile::new("p/libadd.a").with_toolchain(toolchain))
+            .unwrap();
+        g.add_edge(add_c, add_o);
+        g.add_edge(add_o, lib);
+        assert!(g.make(), "build should succeed");
+        (
+            g.rebuild_reasons.get(&add_o).cloned(),
+            g.rebuild_reasons.get(&lib).cloned(),
+        )
+    };
+
+    build(Toolchain::gnu());
+    assert_eq!(build(Toolchain::gnu()), (None, None));
+
+    // Another archiver
+    let toolchain = Toolchain {
+        ar: wrapper(bin.path(), "my-ar", "ar", &log),
+        ..Toolchain::gnu()
+    };
+    let (add_o, lib) = build(toolchain.clone());
+    assert_eq!(add_o, None);
+    assert!(matches!(
+        lib,
+        Some(RebuildReason::FingerprintChanged { .. })
+    ));
+
+    // Another environment
+    let toolchain = Toolchain {
+        env: vec![("TOOLCHAIN_MARK".to_string(), "marked".to_string())],
+        ..toolchain
+    };
+    let (add_o, _) = build(toolchain);
+    assert!(matches!(
+        add_o,
+        Some(RebuildReason::FingerprintChanged { .. })
+    ));
+}
@@ -0,0 +1,125 @@
+//! Test link flags, library search paths and system libraries of XFile.
+
+use petgraph::graph::NodeIndex;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempdir::TempDir;
+use yamake::c_nodes::{CFile, OFile, Toolchain, XFile};
+use yamake::model::{G, GNodeStatus, RebuildReason};
+
+const MAIN_C: &str = "#include <math.h>\n\
+int helper(void);\n\
+int main(void) { volatile double x = 16.0; return (int)sqrt(x) + helper() - 6; }\n";
+
+/// Builds `libhelper.a` in `dir`, outside of the sandbox.
+fn build_helper(dir: &Path) {
+    fs::write(dir.join("helper.c"), "int helper(void) { return 2; }\n").unwrap();
+    let ok = Command::new("gcc")
+        .current_dir(dir)
+        .args(["-c", "helper.c", "-o", "helper.o"])
+        .status()
+        .unwrap()
+        .success()
+        && Command::new("ar")
+            .current_dir(dir)
+            .args(["rcs", "libhelper.a", "helper.o"])
+            .status()
+            .unwrap()
+            .success();
+    assert!(ok, "libhelper.a should build");
+}
+
+fn setup(
+    srcdir: &Path,
+    sandbox: &Path,
+    lib_dir: &Path,
+    link_flags: Vec<String>,
+    toolchain: Toolchain,
+) -> (G, NodeIndex) {
+    fs::create_dir_all(srcdir.join("p")).unwrap();
+    fs::write(srcdir.join("p/main.c"), MAIN_C).unwrap();
+
+    let mut g = G::new(srcdir.to_path_buf(), sandbox.to_path_buf());
+    let main_c = g.add_root_node(CFile::new("p/main.c")).unwrap();
+    let main_o = g.add_node(OFile::new("p/main.o", vec![], vec![])).unwrap();
+    let app = g
+        .add_node(
+            XFile::new("p/app")
+                .with_toolchain(toolchain)
+                .with_link_flags(link_flags)
+                .with_lib_paths(vec![lib_dir.to_path_buf()])
+                .with_libs(vec!["helper".to_string(), "m".to_string()]),
+        )
+        .unwrap();
+    g.add_edge(main_c, main_o);
+    g.add_edge(main_o, app);
+    (g, app)
+}
+
+/// Tests that an application links against a library found through a search
+/// path and against libm, and that changing the link settings relinks it.
+/// The C++ compiler is not the linker of C objects: changing it does not.
+#[test]
+fn test_xfile_link_settings() {
+    let srcdir = TempDir::new("yamake_link_srcdir").unwrap();
+    let sandbox = TempDir::new("yamake_link_sandbox").unwrap();
+    let lib_dir = TempDir::new("yamake_link_lib").unwrap();
+    build_helper(lib_dir.path());
+
+    let (mut g, _) = setup(
+        srcdir.path(),
+        sandbox.path(),
+        lib_dir.path(),
+        vec![],
+        Toolchain::default(),
+    );
+    assert!(g.make(), "First build should succeed");
+    let status = Command::new(sandbox.path().join("p/app")).status().unwrap();
+    assert!(status.success(), "app should return 0");
+
+    // Same settings: nothing to relink
+    let (mut g, app2) = setup(
+        srcdir.path(),
+        sandbox.path(),
+        lib_dir.path(),
+        vec![],
+        Toolchain::default(),
+    );
+    assert!(g.make(), "Second build should succeed");
+    assert_eq!(
+        g.nodes_status.get(&app2),
+        Some(&GNodeStatus::BuildNotRequired)
+    );
+
+    // Another C++ compiler: nothing to relink either
+    let (mut g, app2) = setup(
+        srcdir.path(),
+        sandbox.path(),
+        lib_dir.path(),
+        vec![],
+        Toolchain {
+            cxx: "clang++".to_string(),
+            ..Toolchain::default()
+        },
+    );
+    assert!(g.make(), "Build with another C++ compiler should succeed");
+    assert_eq!(
+        g.nodes_status.get(&app2),
+        Some(&GNodeStatus::BuildNotRequired)
+    );
+
+    // A new link flag relinks the application
+    let (mut g, app3) = setup(
+        srcdir.path(),
+        sandbox.path(),
+        lib_dir.path(),
+        vec!["-Wl,-z,now".to_string()],
+        Toolchain::default(),
+    );
+    assert!(g.make(), "Third build should succeed");
+    assert!(matches!(
+        g.rebuild_reasons.get(&app3),
+        Some(RebuildReason::FingerprintChanged { .. })
+    ));
+}